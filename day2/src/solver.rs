@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{empty, sink};
+
+use intcode::{search, Candidate, Intcode};
+
+/// Nouns and verbs are each tried over this inclusive range.
+const MAX_INPUT: i64 = 99;
+
+/// A polynomial in the noun and verb, as coefficients keyed by (noun power, verb power).
+#[derive(Debug, Clone, PartialEq)]
+struct Poly(BTreeMap<(u32, u32), i64>);
+
+impl Poly {
+    fn constant(value: i64) -> Self {
+        Self::term((0, 0), value)
+    }
+
+    fn term(powers: (u32, u32), coefficient: i64) -> Self {
+        let mut terms = BTreeMap::new();
+        if coefficient != 0 {
+            terms.insert(powers, coefficient);
+        }
+        Poly(terms)
+    }
+
+    fn as_constant(&self) -> Option<i64> {
+        match self.0.keys().all(|powers| *powers == (0, 0)) {
+            true => Some(self.coefficient((0, 0))),
+            false => None,
+        }
+    }
+
+    fn coefficient(&self, powers: (u32, u32)) -> i64 {
+        self.0.get(&powers).copied().unwrap_or(0)
+    }
+
+    fn add(&self, other: &Poly) -> Option<Poly> {
+        let mut terms = self.0.clone();
+        for (powers, coefficient) in &other.0 {
+            let sum = terms.get(powers).unwrap_or(&0).checked_add(*coefficient)?;
+            match sum {
+                0 => terms.remove(powers),
+                _ => terms.insert(*powers, sum),
+            };
+        }
+        Some(Poly(terms))
+    }
+
+    fn mul(&self, other: &Poly) -> Option<Poly> {
+        let mut product = Poly::constant(0);
+        for ((n1, v1), c1) in &self.0 {
+            for ((n2, v2), c2) in &other.0 {
+                product = product.add(&Poly::term((n1 + n2, v1 + v2), c1.checked_mul(*c2)?))?;
+            }
+        }
+        Some(product)
+    }
+
+    /// The constant, noun and verb coefficients, if there are no other terms.
+    fn linear(&self) -> Option<(i64, i64, i64)> {
+        let linear = [(0, 0), (1, 0), (0, 1)];
+        match self.0.keys().all(|powers| linear.contains(powers)) {
+            true => Some((
+                self.coefficient((0, 0)),
+                self.coefficient((1, 0)),
+                self.coefficient((0, 1)),
+            )),
+            false => None,
+        }
+    }
+}
+
+/// Runs the program with the noun and verb as unknowns and returns what ends up at address 0.
+/// Cells whose value can't be tracked, such as those read through an address that depends on
+/// the unknowns, become `None`. Returns `None` if the program does anything that can't be
+/// followed symbolically, like branching or writing through an unknown address.
+fn symbolic_result(program: &[i64]) -> Option<Poly> {
+    let mut memory = program
+        .iter()
+        .map(|value| Some(Poly::constant(*value)))
+        .collect::<Vec<_>>();
+    *memory.get_mut(1)? = Some(Poly::term((1, 0), 1));
+    *memory.get_mut(2)? = Some(Poly::term((0, 1), 1));
+
+    let constant = |cell: &Option<Poly>| cell.as_ref()?.as_constant();
+    let mut ip = 0;
+    loop {
+        match constant(memory.get(ip)?)? {
+            99 => return memory[0].clone(),
+            opcode @ 1..=2 => {
+                let parameters = memory.get(ip + 1..ip + 4)?;
+                // Reads through an unknown address give an unknown value.
+                let read = |parameter: &Option<Poly>| match constant(parameter)
+                    .and_then(|a| usize::try_from(a).ok())
+                {
+                    Some(address) => match memory.get(address) {
+                        Some(cell) => cell.clone(),
+                        None => Some(Poly::constant(0)),
+                    },
+                    None => None,
+                };
+                let value = match (read(&parameters[0]), read(&parameters[1])) {
+                    (Some(a), Some(b)) if opcode == 1 => a.add(&b),
+                    (Some(a), Some(b)) => a.mul(&b),
+                    _ => None,
+                };
+                let address = usize::try_from(constant(&parameters[2])?).ok()?;
+                *memory.get_mut(address)? = value;
+                ip += 4;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Runs the program with a noun and verb, returning the value left at address 0.
+pub fn run(program: &[i64], noun: i64, verb: i64) -> Option<i64> {
+    let mut program = program.to_vec();
+    program[1] = noun;
+    program[2] = verb;
+    let mut computer = Intcode::new(program);
+    computer.compute(empty(), sink()).ok()?;
+    Some(computer.memory[0])
+}
+
+/// Finds the noun and verb, each in `0..=99`, that make the program leave `target` at address
+/// 0, preferring the smallest noun and then the smallest verb.
+pub fn solve(program: &[i64], target: i64) -> Option<(i64, i64)> {
+    match symbolic_result(program).and_then(|result| result.linear()) {
+        Some((constant, noun_coefficient, verb_coefficient)) => (0..=MAX_INPUT).find_map(|noun| {
+            let remainder = target
+                .checked_sub(constant)?
+                .checked_sub(noun_coefficient.checked_mul(noun)?)?;
+            let verb = match verb_coefficient {
+                0 if remainder == 0 => 0,
+                0 => return None,
+                c if remainder % c == 0 => remainder / c,
+                _ => return None,
+            };
+            match (0..=MAX_INPUT).contains(&verb) {
+                true => Some((noun, verb)),
+                false => None,
+            }
+        }),
+        None => sweep(program, target),
+    }
+}
+
+/// Tries every noun and verb across all cores.
+fn sweep(program: &[i64], target: i64) -> Option<(i64, i64)> {
+    let candidates = (0..=MAX_INPUT).flat_map(|noun| {
+        (0..=MAX_INPUT).map(move |verb| Candidate {
+            patches: [(1, noun), (2, verb)].to_vec(),
+            input: Vec::new(),
+        })
+    });
+    let (best, ()) = search(
+        &Intcode::new(program.to_vec()),
+        candidates,
+        |machine, _| match machine.memory[0] == target {
+            true => Some(()),
+            false => None,
+        },
+    )?;
+    Some((best.patches[0].1, best.patches[1].1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbolic() {
+        // [0] = noun * 3 + verb, after a first instruction that reads through the unknowns.
+        let program = [1, 0, 0, 3, 2, 1, 14, 0, 1, 0, 2, 0, 99, 0, 3];
+        let result = symbolic_result(&program).unwrap();
+        assert_eq!(result.linear(), Some((0, 3, 1)));
+        assert_eq!(solve(&program, 3 * 99 + 99), Some((99, 99)));
+        assert_eq!(solve(&program, 3 * 12 + 5), Some((0, 41)));
+        assert_eq!(solve(&program, 3 * 100 + 99), None);
+
+        // [0] = noun * verb isn't linear, so it's found by the sweep.
+        let program = [1, 0, 0, 3, 2, 1, 2, 0, 99];
+        assert_eq!(symbolic_result(&program).unwrap().linear(), None);
+        assert_eq!(solve(&program, 99 * 98), Some((98, 99)));
+        assert_eq!(run(&program, 6, 7), Some(42));
+    }
+}
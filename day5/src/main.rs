@@ -1,28 +1,15 @@
-use std::fs::File;
-use std::io::prelude::*;
 use std::io::{self};
 
-use intcode::Intcode;
+use intcode::{load_program, Console, ConsoleMode, Intcode};
 
 fn main() -> Result<(), io::Error> {
-    let mut file = File::open("input.txt")?;
-    let mut program = String::new();
-    file.read_to_string(&mut program)?;
+    let program = load_program("input.txt")?;
 
-    let program = program
-        .trim()
-        .split(',')
-        .map(|i| i.parse::<i64>().unwrap())
-        .collect::<Vec<_>>();
-    
     let mut computer = Intcode::new(program);
 
-    let stdio = io::stdin();
-    let input = stdio.lock();
-
-    let output = io::stdout();
-
-    computer.compute(input, output);
+    let stdin = io::stdin();
+    let mut console = Console::new(stdin.lock(), io::stdout(), ConsoleMode::Numeric);
+    console.run(&mut computer).expect("Program failed.");
 
     Ok(())
 }
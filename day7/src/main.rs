@@ -1,37 +1,28 @@
-use std::fs::File;
-use std::io::prelude::*;
+use std::collections::VecDeque;
 use std::io::{self};
 
-use intcode::{ExitStatus, Intcode};
+use intcode::{load_program, search_by, Intcode, Network, Route};
 
 use permute::permutations_of;
 
 fn main() -> Result<(), io::Error> {
-    let mut file = File::open("input.txt")?;
-    let mut program = String::new();
-    file.read_to_string(&mut program)?;
-
-    let program = program
-        .trim()
-        .split(',')
-        .map(|i| i.parse::<i64>().unwrap())
-        .collect::<Vec<_>>();
+    let program = load_program("input.txt")?;
 
     let computer = Intcode::new(program);
 
-    let max = permutations_of(&[0, 1, 2, 3, 4])
-        .map(permutation_to_array)
-        .map(|permutation| amplifier_sequence(computer.clone(), &permutation))
-        .max()
-        .unwrap();
+    let (_, max) = search_by(
+        permutations_of(&[0, 1, 2, 3, 4]).map(permutation_to_array),
+        |permutation| Some(amplifier_sequence(computer.clone(), permutation)),
+    )
+    .unwrap();
 
     println!("{}", max);
 
-    let max = permutations_of(&[5, 6, 7, 8, 9])
-        .map(permutation_to_array)
-        .map(|permutation| amplifier_sequence_loop(computer.clone(), &permutation))
-        .max()
-        .unwrap();
+    let (_, max) = search_by(
+        permutations_of(&[5, 6, 7, 8, 9]).map(permutation_to_array),
+        |permutation| Some(amplifier_sequence_loop(computer.clone(), permutation)),
+    )
+    .unwrap();
 
     println!("{}", max);
 
@@ -43,57 +34,47 @@ where
     I: Iterator<Item = &'a u32>,
 {
     let mut permutation_array: [u32; 5] = Default::default();
-    for i in 0..5 {
-        permutation_array[i] = *permutation.next().unwrap();
+    for phase in permutation_array.iter_mut() {
+        *phase = *permutation.next().unwrap();
     }
     permutation_array
 }
 
 fn amplifier_sequence(computer: Intcode, phase_settings: &[u32; 5]) -> i64 {
-    let mut input_signal = String::from("0");
+    let mut input_signal = 0;
 
     for phase in phase_settings.iter() {
         let mut computer = computer.clone();
 
-        let input = format!("{}\n{}", phase.to_string(), input_signal);
+        let mut input = VecDeque::from([i64::from(*phase), input_signal]);
         let mut output = Vec::new();
 
-        computer.compute(input.as_bytes(), &mut output);
+        computer
+            .compute(&mut input, &mut output)
+            .expect("Amplifier program failed.");
 
-        input_signal = String::from_utf8(output).unwrap();
+        input_signal = *output.last().expect("Unexpected final amplifier value");
     }
 
     input_signal
-        .trim()
-        .parse()
-        .expect("Unexpected final amplifier value")
 }
 
 fn amplifier_sequence_loop(computer: Intcode, phase_settings: &[u32; 5]) -> i64 {
-    let mut computers = (0..5).map(|_| computer.clone()).collect::<Vec<_>>();
-    let mut input_signal = String::from("0");
-    let mut exit_status: ExitStatus = ExitStatus::AwaitingInput;
-    let mut first = true;
-
-    while exit_status != ExitStatus::Terminated {
-        for (i, phase) in phase_settings.iter().enumerate() {
-            let input = if first {
-                format!("{}\n{}", phase.to_string(), input_signal)
-            } else {
-                input_signal
-            };
-            let mut output = Vec::new();
-
-            exit_status = computers[i].compute(input.as_bytes(), &mut output);
-
-            input_signal = String::from_utf8(output).unwrap();
-        }
-        first = false;
+    let mut network = Network::new();
+    for phase in phase_settings {
+        let i = network.add(computer.clone());
+        network.send(i, i64::from(*phase));
+    }
+    for i in 0..network.len() {
+        network.route(i, Route::Link((i + 1) % network.len()));
     }
+    network.send(0, 0);
 
-    input_signal
-        .trim()
-        .parse()
+    network.run().expect("Amplifier program failed.");
+
+    *network
+        .input(0)
+        .back()
         .expect("Unexpected final amplifier value")
 }
 
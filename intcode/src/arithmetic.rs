@@ -0,0 +1,85 @@
+use crate::{ErrorKind, Intcode};
+
+/// How addition, multiplication and relative base arithmetic behave when the result doesn't fit
+/// in an `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arithmetic {
+    /// Overflow is an `ErrorKind::Overflow` error at the instruction that caused it.
+    #[default]
+    Checked,
+    /// Results wrap around in two's complement.
+    Wrapping,
+}
+
+impl Arithmetic {
+    /// The policy's name in snapshots.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Checked => "checked",
+            Self::Wrapping => "wrapping",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "checked" => Some(Self::Checked),
+            "wrapping" => Some(Self::Wrapping),
+            _ => None,
+        }
+    }
+
+    pub fn add(self, a: i64, b: i64) -> Result<i64, ErrorKind> {
+        match self {
+            Self::Checked => a.checked_add(b).ok_or(ErrorKind::Overflow),
+            Self::Wrapping => Ok(a.wrapping_add(b)),
+        }
+    }
+
+    pub fn mul(self, a: i64, b: i64) -> Result<i64, ErrorKind> {
+        match self {
+            Self::Checked => a.checked_mul(b).ok_or(ErrorKind::Overflow),
+            Self::Wrapping => Ok(a.wrapping_mul(b)),
+        }
+    }
+}
+
+impl Intcode {
+    pub fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{empty, sink};
+
+    use super::*;
+
+    #[test]
+    fn test_overflow() {
+        // Squares the cell at 9, then adds i64::MAX to the cell at 10.
+        let program = [2, 9, 9, 9, 1, 11, 10, 10, 99, 1 << 32, 0, i64::MAX].to_vec();
+        let mut comp = Intcode::new(program.clone());
+        assert_eq!(comp.arithmetic(), Arithmetic::Checked);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Overflow);
+        assert_eq!(error.instruction_ptr, 0);
+        assert_eq!(comp.memory[9], 1 << 32);
+
+        let mut comp = Intcode::new(program);
+        comp.set_arithmetic(Arithmetic::Wrapping);
+        comp.compute(empty(), sink()).unwrap();
+        assert_eq!(comp.memory[9], 0);
+        assert_eq!(comp.memory[10], i64::MAX);
+
+        // Changing the relative base is checked too.
+        let mut comp = Intcode::new([109, i64::MAX, 109, 1, 99].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.instruction_ptr, 2);
+        assert_eq!(comp.relative_base(), i64::MAX);
+    }
+}
@@ -0,0 +1,415 @@
+//! A small assembly language for Intcode.
+//!
+//! Each line holds an optional label, then an instruction or directive, then an optional
+//! `;` comment. Listings from the disassembler assemble back to the same program, so a leading
+//! `0012:` address column is accepted and ignored.
+//!
+//! ```
+//! let source = "
+//!         .local x 0           ; x names a relative-base offset
+//! start:  IN rb+x              ; operands are [pos], #imm or rb+off
+//!         MUL rb+x, #2, [out]
+//!         OUT [out]
+//!         JT #1, #start
+//! out:    .data 0
+//! ";
+//! assert!(intcode::asm::assemble(source).is_ok());
+//! ```
+//!
+//! Directives are `.data` for literal cells, `.zero N` for `N` zeroed cells, `.equ` for named
+//! constants and `.local` for named relative-base offsets.
+//!
+//! Operand values are sums and differences of integers and symbols. Labels may be used before
+//! they are defined; `.equ` constants and `.local` offsets must be defined first, and locals
+//! may only appear in relative operands.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::{Operation, ParameterMode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    UnknownDirective(String),
+    /// The mnemonic does not take this many operands.
+    OperandCount(String, usize),
+    BadOperand(String),
+    UnknownSymbol(String),
+    DuplicateLabel(String),
+    /// A `.local` offset used outside a relative operand.
+    LocalOutsideRelative(String),
+}
+
+impl fmt::Display for AsmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownMnemonic(m) => write!(f, "unknown mnemonic {}", m),
+            Self::UnknownDirective(d) => write!(f, "unknown directive {}", d),
+            Self::OperandCount(m, n) => write!(f, "{} does not take {} operands", m, n),
+            Self::BadOperand(o) => write!(f, "bad operand {:?}", o),
+            Self::UnknownSymbol(s) => write!(f, "unknown symbol {}", s),
+            Self::DuplicateLabel(l) => write!(f, "duplicate label {}", l),
+            Self::LocalOutsideRelative(s) => {
+                write!(f, "local {} used outside a relative operand", s)
+            }
+        }
+    }
+}
+
+/// An assembly error and the (one-based) source line it occurred on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub kind: AsmErrorKind,
+    pub line: usize,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl Error for AsmError {}
+
+const MNEMONICS: [&str; 10] = [
+    "ADD", "MUL", "IN", "OUT", "JT", "JF", "LT", "EQ", "ARB", "HLT",
+];
+
+enum Statement<'a> {
+    Instruction(&'a str, Vec<&'a str>),
+    Data(Vec<&'a str>),
+    Zero(&'a str),
+    Equ(&'a str, &'a str),
+    Local(&'a str, &'a str),
+}
+
+struct SourceLine<'a> {
+    number: usize,
+    address: usize,
+    statement: Statement<'a>,
+}
+
+#[derive(Default)]
+struct Symbols {
+    labels: HashMap<String, i64>,
+    constants: HashMap<String, i64>,
+    locals: HashMap<String, i64>,
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut symbols = Symbols::default();
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    for (i, text) in source.lines().enumerate() {
+        let number = i + 1;
+        let error = |kind| AsmError { kind, line: number };
+        let mut text = text.split(';').next().unwrap_or("").trim();
+        while let Some((label, rest)) = split_label(text) {
+            if !label.chars().all(|c| c.is_ascii_digit())
+                && symbols
+                    .labels
+                    .insert(label.to_string(), address as i64)
+                    .is_some()
+            {
+                return Err(error(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+            text = rest;
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (head, rest) = match text.find(char::is_whitespace) {
+            Some(i) => (&text[..i], text[i..].trim()),
+            None => (text, ""),
+        };
+        let statement = match head {
+            ".data" => Statement::Data(split_operands(rest)),
+            ".zero" => Statement::Zero(rest),
+            ".equ" | ".local" => {
+                let mut parts = rest.splitn(2, char::is_whitespace);
+                let name = parts.next().unwrap_or("");
+                let value = parts.next().unwrap_or("").trim();
+                if !is_symbol(name) || value.is_empty() {
+                    return Err(error(AsmErrorKind::BadOperand(rest.to_string())));
+                }
+                match head {
+                    ".equ" => Statement::Equ(name, value),
+                    _ => Statement::Local(name, value),
+                }
+            }
+            d if d.starts_with('.') => {
+                return Err(error(AsmErrorKind::UnknownDirective(d.to_string())))
+            }
+            m => Statement::Instruction(m, split_operands(rest)),
+        };
+        // Constants are bound now so that `.zero` sizes can use them. One that refers to a
+        // later label is bound once every label is known, when the program is emitted.
+        if let Statement::Equ(name, value) = statement {
+            if let Ok(value) = symbols.eval(value, false) {
+                symbols.constants.insert(name.to_string(), value);
+            }
+        }
+
+        let line = SourceLine {
+            number,
+            address,
+            statement,
+        };
+        address += line.size(&symbols).map_err(error)?;
+        lines.push(line);
+    }
+
+    let mut program = Vec::with_capacity(address);
+    for line in lines.iter() {
+        line.emit(&mut symbols, &mut program)
+            .map_err(|kind| AsmError {
+                kind,
+                line: line.number,
+            })?;
+    }
+    Ok(program)
+}
+
+impl<'a> SourceLine<'a> {
+    fn size(&self, symbols: &Symbols) -> Result<usize, AsmErrorKind> {
+        Ok(match &self.statement {
+            Statement::Instruction(_, operands) => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+            Statement::Zero(count) => {
+                let count = symbols.eval(count, false)?;
+                if count < 0 {
+                    return Err(AsmErrorKind::BadOperand(count.to_string()));
+                }
+                count as usize
+            }
+            Statement::Equ(_, _) | Statement::Local(_, _) => 0,
+        })
+    }
+
+    fn emit(&self, symbols: &mut Symbols, program: &mut Vec<i64>) -> Result<(), AsmErrorKind> {
+        match &self.statement {
+            Statement::Instruction(mnemonic, operands) => {
+                let operands = operands
+                    .iter()
+                    .map(|operand| symbols.operand(operand))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let modes = operands.iter().map(|(mode, _)| *mode).collect::<Vec<_>>();
+                let operation = Operation::from_mnemonic(mnemonic, &modes).ok_or_else(|| {
+                    match MNEMONICS.contains(&mnemonic.to_uppercase().as_str()) {
+                        true => AsmErrorKind::OperandCount(mnemonic.to_string(), modes.len()),
+                        false => AsmErrorKind::UnknownMnemonic(mnemonic.to_string()),
+                    }
+                })?;
+                program.push(operation.encode());
+                program.extend(operands.iter().map(|(_, value)| *value));
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    program.push(symbols.eval(value, false)?);
+                }
+            }
+            Statement::Zero(_) => {
+                let end = self.address + self.size(symbols)?;
+                program.resize(end, 0);
+            }
+            Statement::Equ(name, value) => {
+                let value = symbols.eval(value, false)?;
+                symbols.constants.insert(name.to_string(), value);
+            }
+            Statement::Local(name, value) => {
+                let value = symbols.eval(value, false)?;
+                symbols.locals.insert(name.to_string(), value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Symbols {
+    fn operand(&self, operand: &str) -> Result<(ParameterMode, i64), AsmErrorKind> {
+        if let Some(inner) = operand.strip_prefix('[').and_then(|o| o.strip_suffix(']')) {
+            Ok((ParameterMode::Position, self.eval(inner, false)?))
+        } else if let Some(value) = operand.strip_prefix('#') {
+            Ok((ParameterMode::Immediate, self.eval(value, false)?))
+        } else if let Some(offset) = operand.strip_prefix("rb") {
+            let offset = offset.trim();
+            let value = match offset {
+                "" => 0,
+                _ if offset.starts_with('+') || offset.starts_with('-') => {
+                    self.eval(&format!("0{}", offset), true)?
+                }
+                _ => return Err(AsmErrorKind::BadOperand(operand.to_string())),
+            };
+            Ok((ParameterMode::Relative, value))
+        } else {
+            Err(AsmErrorKind::BadOperand(operand.to_string()))
+        }
+    }
+
+    /// Evaluates a sum of integers and symbols.
+    fn eval(&self, expression: &str, relative: bool) -> Result<i64, AsmErrorKind> {
+        let bad = || AsmErrorKind::BadOperand(expression.to_string());
+        let mut total = 0i64;
+        let mut sign = 1i64;
+        let mut rest = expression.trim();
+        loop {
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            let (term, tail) = match end {
+                0 if rest.is_empty() => return Err(bad()),
+                0 => {
+                    // A leading sign belongs to a negative literal such as `#-4`.
+                    let next = rest[1..].find(['+', '-']).map_or(rest.len(), |i| i + 1);
+                    (&rest[..next], &rest[next..])
+                }
+                _ => (&rest[..end], &rest[end..]),
+            };
+            let value = self.term(term.trim(), relative).ok_or_else(bad)??;
+            total = total.wrapping_add(sign.wrapping_mul(value));
+            match tail.chars().next() {
+                None => return Ok(total),
+                Some(op) => {
+                    sign = if op == '-' { -1 } else { 1 };
+                    rest = tail[1..].trim();
+                    if rest.is_empty() {
+                        return Err(bad());
+                    }
+                }
+            }
+        }
+    }
+
+    fn term(&self, term: &str, relative: bool) -> Option<Result<i64, AsmErrorKind>> {
+        if let Ok(value) = term.parse::<i64>() {
+            return Some(Ok(value));
+        }
+        if !is_symbol(term) {
+            return None;
+        }
+        if let Some(value) = self.locals.get(term) {
+            return Some(match relative {
+                true => Ok(*value),
+                false => Err(AsmErrorKind::LocalOutsideRelative(term.to_string())),
+            });
+        }
+        Some(
+            self.labels
+                .get(term)
+                .or_else(|| self.constants.get(term))
+                .copied()
+                .ok_or_else(|| AsmErrorKind::UnknownSymbol(term.to_string())),
+        )
+    }
+}
+
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let colon = text.find(':')?;
+    let label = text[..colon].trim();
+    if is_symbol(label) || (!label.is_empty() && label.chars().all(|c| c.is_ascii_digit())) {
+        Some((label, text[colon + 1..].trim()))
+    } else {
+        None
+    }
+}
+
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    text.split(',').map(|operand| operand.trim()).collect()
+}
+
+fn is_symbol(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            start:  IN rb+x      ; x is defined below, but locals are bound in order
+        ";
+        assert_eq!(
+            assemble(source).unwrap_err(),
+            AsmError {
+                kind: AsmErrorKind::UnknownSymbol("x".to_string()),
+                line: 2
+            }
+        );
+
+        let source = "
+                    .local x 1
+                    .equ twice 2
+            start:  IN rb+x
+                    MUL rb+x, #twice, [out]
+                    OUT [out]
+                    JF [out], #end
+                    ARB #-1
+                    EQ rb, #0, [out + 0]
+            end:    HLT
+            out:    .data 0
+                    .zero twice
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            [203, 1, 1202, 1, 2, 18, 4, 18, 1006, 18, 17, 109, -1, 1208, 0, 0, 18, 99, 0, 0, 0]
+        );
+        assert_eq!(
+            assemble(".equ size end - start\nstart: .zero 3\nend: .data size").unwrap(),
+            [0, 0, 0, 3]
+        );
+        assert_eq!(
+            assemble(".data 0 - -9223372036854775808").unwrap(),
+            [i64::MIN]
+        );
+
+        assert_eq!(
+            assemble("ADD #1, #2").unwrap_err().kind,
+            AsmErrorKind::OperandCount("ADD".to_string(), 2)
+        );
+        assert_eq!(
+            assemble("NOP").unwrap_err().kind,
+            AsmErrorKind::UnknownMnemonic("NOP".to_string())
+        );
+        assert_eq!(
+            assemble(".local x 1\nOUT [x]").unwrap_err(),
+            AsmError {
+                kind: AsmErrorKind::LocalOutsideRelative("x".to_string()),
+                line: 2
+            }
+        );
+        assert_eq!(
+            assemble("a: HLT\na: HLT").unwrap_err().kind,
+            AsmErrorKind::DuplicateLabel("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = [
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99, 109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006,
+            101, 0, 99, 100099, 1107,
+        ];
+        let listing = disassemble(&program)
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(assemble(&listing).unwrap(), program);
+    }
+}
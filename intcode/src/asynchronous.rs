@@ -0,0 +1,301 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::{self, Future};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::{ErrorKind, ExitStatus, Intcode, IntcodeError};
+
+/// How many instructions `run_async` executes between yields to the executor.
+const YIELD_INTERVAL: u32 = 1024;
+
+/// An asynchronous source of values for the `Input` instruction.
+pub trait AsyncInput {
+    /// The next value, or `None` if no more input will arrive.
+    fn read(&mut self) -> impl Future<Output = Result<Option<i64>, ErrorKind>> + '_;
+}
+
+/// An asynchronous destination for values from the `Output` instruction.
+pub trait AsyncOutput {
+    fn write(&mut self, value: i64) -> impl Future<Output = Result<(), ErrorKind>> + '_;
+}
+
+impl<T: AsyncInput + ?Sized> AsyncInput for &mut T {
+    fn read(&mut self) -> impl Future<Output = Result<Option<i64>, ErrorKind>> + '_ {
+        (**self).read()
+    }
+}
+
+impl<T: AsyncOutput + ?Sized> AsyncOutput for &mut T {
+    fn write(&mut self, value: i64) -> impl Future<Output = Result<(), ErrorKind>> + '_ {
+        (**self).write(value)
+    }
+}
+
+impl AsyncInput for VecDeque<i64> {
+    fn read(&mut self) -> impl Future<Output = Result<Option<i64>, ErrorKind>> + '_ {
+        future::ready(Ok(self.pop_front()))
+    }
+}
+
+impl AsyncOutput for Vec<i64> {
+    fn write(&mut self, value: i64) -> impl Future<Output = Result<(), ErrorKind>> + '_ {
+        self.push(value);
+        future::ready(Ok(()))
+    }
+}
+
+struct Shared {
+    queue: VecDeque<i64>,
+    senders: usize,
+    waker: Option<Waker>,
+}
+
+/// The sending half of a `pipe`. Sending never waits.
+pub struct PipeSender(Rc<RefCell<Shared>>);
+
+/// The receiving half of a `pipe`. Reading waits until a value is sent, and reports the end
+/// of input once every sender has been dropped.
+pub struct PipeReceiver(Rc<RefCell<Shared>>);
+
+/// An unbounded single-threaded channel for connecting machines on one executor.
+pub fn pipe() -> (PipeSender, PipeReceiver) {
+    let shared = Rc::new(RefCell::new(Shared {
+        queue: VecDeque::new(),
+        senders: 1,
+        waker: None,
+    }));
+    (PipeSender(shared.clone()), PipeReceiver(shared))
+}
+
+impl PipeSender {
+    pub fn send(&self, value: i64) {
+        let mut shared = self.0.borrow_mut();
+        shared.queue.push_back(value);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Clone for PipeSender {
+    fn clone(&self) -> Self {
+        self.0.borrow_mut().senders += 1;
+        PipeSender(self.0.clone())
+    }
+}
+
+impl Drop for PipeSender {
+    fn drop(&mut self) {
+        let mut shared = self.0.borrow_mut();
+        shared.senders -= 1;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl PipeReceiver {
+    /// The next value if one has already been sent.
+    pub fn try_recv(&mut self) -> Option<i64> {
+        self.0.borrow_mut().queue.pop_front()
+    }
+}
+
+impl AsyncInput for PipeReceiver {
+    fn read(&mut self) -> impl Future<Output = Result<Option<i64>, ErrorKind>> + '_ {
+        future::poll_fn(move |cx| {
+            let mut shared = self.0.borrow_mut();
+            match shared.queue.pop_front() {
+                Some(value) => Poll::Ready(Ok(Some(value))),
+                None if shared.senders == 0 => Poll::Ready(Ok(None)),
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl AsyncOutput for PipeSender {
+    fn write(&mut self, value: i64) -> impl Future<Output = Result<(), ErrorKind>> + '_ {
+        self.send(value);
+        future::ready(Ok(()))
+    }
+}
+
+impl Intcode {
+    /// Like `compute`, but awaits input and output. A machine waiting for input yields to the
+    /// executor instead of returning `AwaitingInput`, which it only does once the input ends.
+    /// It also yields every so often while computing, so machines sharing an executor all make
+    /// progress.
+    pub async fn run_async<I, O>(
+        &mut self,
+        mut input: I,
+        mut output: O,
+    ) -> Result<ExitStatus, IntcodeError>
+    where
+        I: AsyncInput,
+        O: AsyncOutput,
+    {
+        let mut pending = VecDeque::new();
+        let mut until_yield = YIELD_INTERVAL;
+        loop {
+            until_yield -= 1;
+            if until_yield == 0 {
+                until_yield = YIELD_INTERVAL;
+                yield_now().await;
+            }
+            let instruction_ptr = self.instruction_ptr;
+            match self.step(&mut pending)? {
+                Some(ExitStatus::Output(value)) => output
+                    .write(value)
+                    .await
+                    .map_err(|e| self.error_at(instruction_ptr, e))?,
+                Some(ExitStatus::AwaitingInput) => {
+                    match input.read().await.map_err(|e| self.error(e))? {
+                        Some(value) => pending.push_back(value),
+                        None => return Ok(ExitStatus::AwaitingInput),
+                    }
+                }
+                Some(status) => return Ok(status),
+                None => {}
+            }
+        }
+    }
+}
+
+/// Returns to the executor once, asking to be polled again straight away.
+fn yield_now() -> impl Future<Output = ()> {
+    let mut yielded = false;
+    future::poll_fn(move |cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+}
+
+struct ThreadWaker {
+    thread: Thread,
+    woken: AtomicBool,
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    join_all(vec![future]).pop().unwrap()
+}
+
+/// Runs futures, such as several machines connected by pipes, to completion on the current
+/// thread and returns their results in order. The thread parks while every future is pending,
+/// so futures that wait on each other forever never return.
+pub fn join_all<F: Future>(futures: Vec<F>) -> Vec<F::Output> {
+    let state = Arc::new(ThreadWaker {
+        thread: thread::current(),
+        woken: AtomicBool::new(true),
+    });
+    let waker = Waker::from(state.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    let mut futures = futures.into_iter().map(Box::pin).collect::<Vec<_>>();
+    let mut outputs = futures.iter().map(|_| None).collect::<Vec<_>>();
+    while outputs.iter().any(Option::is_none) {
+        if !state.woken.swap(false, Ordering::Acquire) {
+            thread::park();
+            continue;
+        }
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_none() {
+                if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                    *output = Some(value);
+                }
+            }
+        }
+    }
+    outputs.into_iter().map(Option::unwrap).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+
+    use super::*;
+
+    #[test]
+    fn test_run_async() {
+        // Outputs 1 if its input is 8, otherwise 0.
+        let program = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec();
+        let mut input = VecDeque::from([8]);
+        let mut output = Vec::new();
+        let status = block_on(Intcode::new(program.clone()).run_async(&mut input, &mut output));
+        assert_eq!(status.unwrap(), ExitStatus::Terminated);
+        assert_eq!(output, [1]);
+
+        let status = block_on(Intcode::new(program).run_async(VecDeque::new(), Vec::new()));
+        assert_eq!(status.unwrap(), ExitStatus::AwaitingInput);
+    }
+
+    #[test]
+    fn test_fair() {
+        // Counts down from 10000 before halting, so it must yield for the other future to run.
+        let program = [1001, 9, -1, 9, 1005, 9, 0, 99, 0, 10000].to_vec();
+        let order = RefCell::new(Vec::new());
+        let mut machine = Intcode::new(program);
+        let busy: Pin<Box<dyn Future<Output = ()>>> = Box::pin(async {
+            machine
+                .run_async(VecDeque::new(), Vec::new())
+                .await
+                .unwrap();
+            order.borrow_mut().push("busy");
+        });
+        let quick: Pin<Box<dyn Future<Output = ()>>> = Box::pin(async {
+            order.borrow_mut().push("quick");
+        });
+        join_all(vec![busy, quick]);
+        assert_eq!(order.into_inner(), ["quick", "busy"]);
+    }
+
+    #[test]
+    fn test_pipes() {
+        // Adds one to its input. Three run in a chain, polled last stage first.
+        let program = [3, 9, 1001, 9, 1, 9, 4, 9, 99, 0].to_vec();
+        let (first, a) = pipe();
+        let (b, c) = pipe();
+        let (d, e) = pipe();
+        let (f, mut last) = pipe();
+        let stages = vec![(a, b), (c, d), (e, f)]
+            .into_iter()
+            .rev()
+            .map(|(input, output)| {
+                let mut machine = Intcode::new(program.clone());
+                async move { machine.run_async(input, output).await }
+            })
+            .collect::<Vec<_>>();
+        first.send(5);
+        for status in join_all(stages) {
+            assert_eq!(status.unwrap(), ExitStatus::Terminated);
+        }
+        assert_eq!(last.try_recv(), Some(8));
+        assert_eq!(last.try_recv(), None);
+
+        // Input ends once the sender is dropped.
+        let (sender, receiver) = pipe();
+        drop(sender);
+        let mut machine = Intcode::new(program);
+        let status = block_on(machine.run_async(receiver, Vec::new()));
+        assert_eq!(status.unwrap(), ExitStatus::AwaitingInput);
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::convert::TryFrom;
+use std::env;
+use std::io::prelude::*;
+use std::io::{self};
+
+use intcode::disasm::decode_memory;
+use intcode::{load_program, load_program_stdin, ExitStatus, Intcode, Operation};
+
+const HELP: &str = "\
+step [n]          execute n instructions (default 1)
+continue          run until a breakpoint, watchpoint, input request or halt
+break [addr]      toggle a breakpoint, or list breakpoints
+watch [addr]      toggle a watchpoint on a memory cell, or list watchpoints
+regs              show the instruction pointer, relative base and next instruction
+list [addr] [n]   disassemble n instructions from addr (default: ip, 10)
+mem addr [n]      show n memory cells from addr (default 1)
+set addr value    write value to a memory cell
+input v...        queue input values
+output            show every value output so far
+back [n]          undo n instructions (default 1)
+origin addr       undo back to the last write of a memory cell
+quit              exit the debugger";
+
+/// How many instructions `back` and `origin` can undo.
+const UNDO_CAPACITY: usize = 1 << 20;
+
+struct Debugger {
+    computer: Intcode,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, i64>,
+    halted: bool,
+}
+
+/// Why execution stopped before the requested number of steps.
+enum Stop {
+    Breakpoint(usize),
+    Watchpoint(usize, i64, i64),
+    AwaitingInput,
+    Terminated,
+    Error(String),
+}
+
+impl Debugger {
+    fn new(mut computer: Intcode) -> Self {
+        computer.enable_undo(Some(UNDO_CAPACITY));
+        Debugger {
+            computer,
+            input: VecDeque::new(),
+            output: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            halted: false,
+        }
+    }
+
+    /// Executes one instruction, reporting anything that should stop a `continue`.
+    fn step(&mut self) -> Result<Option<Stop>, Stop> {
+        if self.halted {
+            return Err(Stop::Terminated);
+        }
+        match self.computer.step(&mut self.input) {
+            Ok(Some(ExitStatus::Output(value))) => self.output.push(value),
+            Ok(Some(ExitStatus::AwaitingInput)) => return Err(Stop::AwaitingInput),
+            Ok(Some(ExitStatus::Terminated)) => {
+                self.halted = true;
+                return Err(Stop::Terminated);
+            }
+            Ok(Some(status)) => return Err(Stop::Error(format!("{:?}", status))),
+            Ok(None) => {}
+            Err(e) => return Err(Stop::Error(e.to_string())),
+        }
+
+        for (address, last) in self.watchpoints.iter_mut() {
+            let value = self.computer.memory.get(*address);
+            if value != *last {
+                let old = *last;
+                *last = value;
+                return Ok(Some(Stop::Watchpoint(*address, old, value)));
+            }
+        }
+        let ip = self.computer.instruction_ptr();
+        if self.breakpoints.contains(&ip) {
+            return Ok(Some(Stop::Breakpoint(ip)));
+        }
+        Ok(None)
+    }
+
+    /// Undoes one instruction, taking back its output if it had one.
+    fn step_back(&mut self) -> bool {
+        match self.computer.step_back() {
+            Some(operation) => {
+                if let Operation::Output(_) = operation {
+                    self.output.pop();
+                }
+                self.halted = false;
+                for (address, last) in self.watchpoints.iter_mut() {
+                    *last = self.computer.memory.get(*address);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Undoes instructions up to the last write to `address`, returning the writer's address.
+    fn origin(&mut self, address: usize) -> Option<usize> {
+        while self.step_back() {
+            if self.computer.write_address() == Some(address) {
+                return Some(self.computer.instruction_ptr());
+            }
+        }
+        None
+    }
+
+    fn run(&mut self, limit: Option<usize>) -> String {
+        let outputs = self.output.len();
+        let mut report = String::new();
+        let mut steps = 0;
+        let stop = loop {
+            if limit == Some(steps) {
+                break None;
+            }
+            steps += 1;
+            match self.step() {
+                Ok(None) => {}
+                Ok(Some(stop)) | Err(stop) => break Some(stop),
+            }
+        };
+        for value in &self.output[outputs..] {
+            report += &format!("output: {}\n", value);
+        }
+        match stop {
+            Some(Stop::Breakpoint(address)) => report += &format!("breakpoint at {}\n", address),
+            Some(Stop::Watchpoint(address, old, new)) => {
+                report += &format!("watchpoint [{}]: {} -> {}\n", address, old, new)
+            }
+            Some(Stop::AwaitingInput) => report += "awaiting input\n",
+            Some(Stop::Terminated) => report += "terminated\n",
+            Some(Stop::Error(e)) => report += &format!("error: {}\n", e),
+            None => {}
+        }
+        report + &self.regs()
+    }
+
+    fn regs(&self) -> String {
+        let ip = self.computer.instruction_ptr();
+        format!(
+            "ip={} rb={}\n{}",
+            ip,
+            self.computer.relative_base(),
+            decode_memory(&self.computer.memory, ip)
+        )
+    }
+
+    fn list(&self, address: usize, count: usize) -> String {
+        let mut address = address;
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            let line = decode_memory(&self.computer.memory, address);
+            address = address.saturating_add(line.size());
+            lines.push(line.to_string());
+        }
+        lines.join("\n")
+    }
+
+    /// Runs one debugger command, returning the text to show and whether to keep going.
+    fn command(&mut self, line: &str) -> (String, bool) {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return (String::new(), true),
+        };
+        let args = words
+            .map(|w| w.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>();
+        let args = match args {
+            Ok(args) => args,
+            Err(_) => return (String::from("arguments must be integers"), true),
+        };
+        if let ("q", []) | ("quit", []) = (command, args.as_slice()) {
+            return (String::new(), false);
+        }
+        match self.execute(command, &args) {
+            Ok(Some(response)) | Err(response) => (response, true),
+            Ok(None) => (
+                format!("unrecognized command: {} (try help)", line.trim()),
+                true,
+            ),
+        }
+    }
+
+    /// Runs a command other than `quit`, or returns `None` if it isn't recognized. Errors are
+    /// messages about bad arguments.
+    fn execute(&mut self, command: &str, args: &[i64]) -> Result<Option<String>, String> {
+        let count = |i: usize, default: usize| match args.get(i) {
+            Some(&n) => usize::try_from(n).map_err(|_| format!("invalid count {}", n)),
+            None => Ok(default),
+        };
+
+        Ok(Some(match (command, args) {
+            ("s", _) | ("step", _) => self.run(Some(count(0, 1)?)),
+            ("c", []) | ("continue", []) => self.run(None),
+            ("b", []) | ("break", []) | ("w", []) | ("watch", []) => {
+                let addresses = match command.starts_with('b') {
+                    true => self.breakpoints.iter().copied().collect::<Vec<_>>(),
+                    false => self.watchpoints.keys().copied().collect(),
+                };
+                format!("{:?}", addresses)
+            }
+            ("b", [a]) | ("break", [a]) => {
+                let a = address(*a)?;
+                match self.breakpoints.remove(&a) {
+                    true => format!("removed breakpoint at {}", a),
+                    false => {
+                        self.breakpoints.insert(a);
+                        format!("breakpoint at {}", a)
+                    }
+                }
+            }
+            ("w", [a]) | ("watch", [a]) => {
+                let a = address(*a)?;
+                match self.watchpoints.remove(&a) {
+                    Some(_) => format!("removed watchpoint on [{}]", a),
+                    None => {
+                        self.watchpoints.insert(a, self.computer.memory.get(a));
+                        format!("watching [{}]", a)
+                    }
+                }
+            }
+            ("r", []) | ("regs", []) => self.regs(),
+            ("l", _) | ("list", _) => {
+                let start = match args.first() {
+                    Some(&a) => address(a)?,
+                    None => self.computer.instruction_ptr(),
+                };
+                self.list(start, count(1, 10)?)
+            }
+            ("m", [a]) | ("mem", [a]) | ("m", [a, _]) | ("mem", [a, _]) => {
+                let a = address(*a)?;
+                (a..a.saturating_add(count(1, 1)?))
+                    .map(|a| format!("[{}] = {}", a, self.computer.memory.get(a)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            ("set", [a, value]) => {
+                let a = address(*a)?;
+                self.computer.memory.set(a, *value);
+                if let Some(last) = self.watchpoints.get_mut(&a) {
+                    *last = *value;
+                }
+                format!("[{}] = {}", a, value)
+            }
+            ("i", values) | ("input", values) => {
+                self.input.extend(values);
+                format!("queued input: {:?}", self.input)
+            }
+            ("o", []) | ("output", []) => format!("{:?}", self.output),
+            ("back", _) => {
+                let undone = (0..count(0, 1)?).take_while(|_| self.step_back()).count();
+                format!("undid {} instructions\n{}", undone, self.regs())
+            }
+            ("origin", [a]) => {
+                let a = address(*a)?;
+                match self.origin(a) {
+                    Some(writer) => format!("last written by {}\n{}", writer, self.regs()),
+                    None => format!("no logged write to [{}]\n{}", a, self.regs()),
+                }
+            }
+            ("h", []) | ("help", []) => String::from(HELP),
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// An address argument, which must not be negative.
+fn address(value: i64) -> Result<usize, String> {
+    usize::try_from(value).map_err(|_| format!("invalid address {}", value))
+}
+
+fn main() -> Result<(), io::Error> {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("input.txt"));
+    let program = match path.as_str() {
+        "-" => load_program_stdin()?,
+        path => load_program(path)?,
+    };
+
+    let mut debugger = Debugger::new(Intcode::new(program));
+    println!("{}", debugger.regs());
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    loop {
+        write!(stdout, "(icdb) ")?;
+        stdout.flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let (response, running) = debugger.command(&line);
+        if !response.is_empty() {
+            println!("{}", response.trim_end());
+        }
+        if !running {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debugger() {
+        let mut debugger =
+            Debugger::new(Intcode::new([3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec()));
+        assert_eq!(
+            debugger.command("list 0 2").0,
+            "0000: IN [9]\n0002: EQ [9], [10], [9]"
+        );
+        assert!(debugger.command("step").0.starts_with("awaiting input"));
+
+        debugger.command("input 8");
+        debugger.command("watch 9");
+        assert!(debugger
+            .command("continue")
+            .0
+            .starts_with("watchpoint [9]: -1 -> 8\nip=2 rb=0"));
+        debugger.command("break 8");
+        assert!(debugger
+            .command("c")
+            .0
+            .starts_with("watchpoint [9]: 8 -> 1"));
+        assert!(debugger
+            .command("c")
+            .0
+            .starts_with("output: 1\nbreakpoint at 8\nip=8"));
+        assert!(debugger.command("c").0.starts_with("terminated\nip=8"));
+        assert_eq!(debugger.command("output").0, "[1]");
+
+        assert!(debugger
+            .command("back 2")
+            .0
+            .starts_with("undid 2 instructions\nip=6"));
+        assert_eq!(debugger.command("output").0, "[]");
+        assert!(debugger
+            .command("origin 9")
+            .0
+            .starts_with("last written by 2\nip=2"));
+        assert_eq!(debugger.command("mem 9").0, "[9] = 8");
+        assert!(debugger
+            .command("c")
+            .0
+            .starts_with("watchpoint [9]: 8 -> 1"));
+        assert!(debugger
+            .command("c")
+            .0
+            .starts_with("output: 1\nbreakpoint at 8"));
+
+        debugger.command("set 10 -5");
+        assert_eq!(debugger.command("mem 9 2").0, "[9] = 1\n[10] = -5");
+        assert_eq!(debugger.command("mem -1 2").0, "invalid address -1");
+        assert_eq!(debugger.command("set -1 5").0, "invalid address -1");
+        assert_eq!(debugger.command("break -3").0, "invalid address -3");
+        assert_eq!(debugger.command("step -1").0, "invalid count -1");
+        let last = format!("mem {} 2", i64::MAX);
+        assert_eq!(debugger.command(&last).0.lines().count(), 2);
+        assert!(debugger
+            .command("nop")
+            .0
+            .starts_with("unrecognized command"));
+        assert!(!debugger.command("quit").1);
+    }
+}
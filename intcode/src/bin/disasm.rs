@@ -0,0 +1,24 @@
+use std::env;
+use std::io::prelude::*;
+use std::io::{self};
+
+use intcode::disasm::disassemble;
+use intcode::{load_program, load_program_stdin};
+
+fn main() -> Result<(), io::Error> {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("input.txt"));
+    let program = match path.as_str() {
+        "-" => load_program_stdin()?,
+        path => load_program(path)?,
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for line in disassemble(&program) {
+        writeln!(stdout, "{}", line)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::env;
+use std::io::{self};
+use std::path::Path;
+
+use intcode::topology::Topology;
+
+fn main() -> Result<(), io::Error> {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("network.txt"));
+    let topology = Topology::load(&path)?;
+    let dir = Path::new(&path).parent().unwrap_or_else(|| Path::new(""));
+    let mut network = topology.network(dir)?;
+
+    let status = network.run().map_err(io::Error::other)?;
+    println!("{:?}", status);
+
+    for (i, spec) in topology.machines.iter().enumerate() {
+        if let Some(stopped) = network.stopped(i) {
+            println!("{}: {:?}", spec.name, stopped);
+        }
+        if !network.output(i).is_empty() {
+            println!("{}: output {:?}", spec.name, network.output(i));
+        }
+        if !network.input(i).is_empty() {
+            println!("{}: unread {:?}", spec.name, network.input(i));
+        }
+    }
+    for packet in network.take_unrouted() {
+        println!("unrouted to {}: {:?}", packet.address, packet.payload);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+
+use crate::io::ascii_line;
+use crate::{AsciiOutput, ErrorKind, ExitStatus, Intcode, IntcodeError, IntcodeOutput, TextOutput};
+
+/// How a console turns lines into input and output into text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsoleMode {
+    /// One integer per line.
+    Numeric,
+    /// Lines of text, as with `AsciiInput` and `AsciiOutput`.
+    Ascii,
+}
+
+/// An interactive terminal for a machine. Output is shown as it arrives, and a prompt is shown
+/// whenever the machine needs input. In numeric mode a line that isn't an integer is rejected
+/// and asked for again.
+pub struct Console<R, W> {
+    reader: R,
+    writer: W,
+    mode: ConsoleMode,
+    prompt: String,
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    pub fn new(reader: R, writer: W, mode: ConsoleMode) -> Self {
+        Console {
+            reader,
+            writer,
+            mode,
+            prompt: String::from("> "),
+        }
+    }
+
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+    }
+
+    /// Runs the machine until it stops or the input ends, in which case it returns
+    /// `AwaitingInput`.
+    pub fn run(&mut self, machine: &mut Intcode) -> Result<ExitStatus, IntcodeError> {
+        let mut input = VecDeque::new();
+        loop {
+            let instruction_ptr = machine.instruction_ptr;
+            let result = match machine.step(&mut input)? {
+                Some(ExitStatus::Output(value)) => self.output(value),
+                Some(ExitStatus::AwaitingInput) => match self.input() {
+                    Ok(Some(values)) => {
+                        input = values;
+                        Ok(())
+                    }
+                    Ok(None) => return Ok(ExitStatus::AwaitingInput),
+                    Err(e) => Err(e),
+                },
+                Some(status) => return Ok(status),
+                None => Ok(()),
+            };
+            result.map_err(|e| machine.error_at(instruction_ptr, e))?;
+        }
+    }
+
+    fn output(&mut self, value: i64) -> Result<(), ErrorKind> {
+        match self.mode {
+            ConsoleMode::Numeric => TextOutput(&mut self.writer).write(value),
+            ConsoleMode::Ascii => AsciiOutput::new(&mut self.writer).write(value),
+        }
+    }
+
+    /// Prompts until a usable line is entered, returning its values, or `None` at the end of
+    /// input.
+    fn input(&mut self) -> Result<Option<VecDeque<i64>>, ErrorKind> {
+        let io_error = |e: std::io::Error| ErrorKind::Io(e.kind());
+        loop {
+            write!(self.writer, "{}", self.prompt).map_err(io_error)?;
+            self.writer.flush().map_err(io_error)?;
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).map_err(io_error)? == 0 {
+                return Ok(None);
+            }
+            match self.mode {
+                ConsoleMode::Ascii => return Ok(Some(ascii_line(&line))),
+                ConsoleMode::Numeric => match line.trim().parse::<i64>() {
+                    Ok(value) => return Ok(Some(VecDeque::from([value]))),
+                    Err(_) => writeln!(self.writer, "not an integer: {:?}", line.trim())
+                        .map_err(io_error)?,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console() {
+        // Outputs 1 if its input is 8, otherwise 0.
+        let mut machine = Intcode::new([3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec());
+        let mut screen = Vec::new();
+        let mut console = Console::new(&b"eight\n8\n"[..], &mut screen, ConsoleMode::Numeric);
+        assert_eq!(console.run(&mut machine).unwrap(), ExitStatus::Terminated);
+        assert_eq!(
+            String::from_utf8(screen).unwrap(),
+            "> not an integer: \"eight\"\n> 1\n"
+        );
+
+        // Echoes one line of text, then outputs 1000.
+        let program = [
+            3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 0, 104, 1000, 99,
+        ];
+        let mut machine = Intcode::new(program.to_vec());
+        let mut screen = Vec::new();
+        let mut console = Console::new(&b"hi\n"[..], &mut screen, ConsoleMode::Ascii);
+        console.set_prompt("? ");
+        assert_eq!(console.run(&mut machine).unwrap(), ExitStatus::Terminated);
+        assert_eq!(String::from_utf8(screen).unwrap(), "? hi\n1000\n");
+
+        let mut machine = Intcode::new([3, 0, 99].to_vec());
+        let mut console = Console::new(&b""[..], Vec::new(), ConsoleMode::Ascii);
+        assert_eq!(
+            console.run(&mut machine).unwrap(),
+            ExitStatus::AwaitingInput
+        );
+    }
+}
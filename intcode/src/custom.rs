@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{ErrorKind, ExitStatus, Intcode, Operation, ParameterMode};
+
+/// An instruction added to a machine with `Intcode::register_opcode`.
+///
+/// Custom instructions are not seen by tracing, profiling or the disassembler. They can't be
+/// undone either: running one clears the undo log, so `step_back` stops after it. Loop
+/// detection treats one like reading input, since it may depend on state outside the machine.
+pub trait CustomOpcode: Send + Sync {
+    fn mnemonic(&self) -> &str;
+
+    /// How many parameters follow the instruction word. Each has its own mode digit, as with
+    /// the built-in instructions.
+    fn parameters(&self) -> usize;
+
+    /// Runs the instruction. Returning a status stops execution as the built-in instructions
+    /// do: `Some(ExitStatus::Output(value))` outputs a value and `Some(ExitStatus::Terminated)`
+    /// halts the machine.
+    fn execute(&self, call: &mut CustomCall) -> Result<Option<ExitStatus>, ErrorKind>;
+}
+
+/// Custom opcodes by number, shared between clones of a machine.
+pub(crate) type CustomOpcodes = Arc<HashMap<i64, Arc<dyn CustomOpcode>>>;
+
+/// A custom instruction being executed, giving access to its parameters and the machine.
+pub struct CustomCall<'a> {
+    machine: &'a mut Intcode,
+    modes: Vec<ParameterMode>,
+    jumped: bool,
+}
+
+impl CustomCall<'_> {
+    /// The value of parameter `i` (counting from zero), resolved through its mode.
+    pub fn read(&self, i: usize) -> Result<i64, ErrorKind> {
+        self.machine.value_from_parameter(i + 1, self.mode(i)?)
+    }
+
+    /// Writes to the address parameter `i` refers to.
+    pub fn write(&mut self, i: usize, value: i64) -> Result<(), ErrorKind> {
+        let mode = self.mode(i)?;
+        self.machine.write(i + 1, value, mode)
+    }
+
+    /// Continues execution at `address` instead of the next instruction.
+    pub fn jump(&mut self, address: i64) -> Result<(), ErrorKind> {
+        self.machine.instruction_ptr = self.machine.address(address)?;
+        self.jumped = true;
+        Ok(())
+    }
+
+    pub fn machine(&mut self) -> &mut Intcode {
+        self.machine
+    }
+
+    fn mode(&self, i: usize) -> Result<ParameterMode, ErrorKind> {
+        self.modes.get(i).copied().ok_or(ErrorKind::InvalidAddress(
+            (self.machine.instruction_ptr + i + 1) as i64,
+        ))
+    }
+}
+
+struct FnOpcode<F> {
+    mnemonic: String,
+    parameters: usize,
+    execute: F,
+}
+
+impl<F> CustomOpcode for FnOpcode<F>
+where
+    F: Fn(&mut CustomCall) -> Result<Option<ExitStatus>, ErrorKind> + Send + Sync,
+{
+    fn mnemonic(&self) -> &str {
+        &self.mnemonic
+    }
+
+    fn parameters(&self) -> usize {
+        self.parameters
+    }
+
+    fn execute(&self, call: &mut CustomCall) -> Result<Option<ExitStatus>, ErrorKind> {
+        (self.execute)(call)
+    }
+}
+
+impl Intcode {
+    /// Adds an instruction with the given two-digit opcode, replacing any custom instruction
+    /// already registered with it. Opcodes stay unknown on other machines, except clones made
+    /// afterwards.
+    ///
+    /// # Panics
+    ///
+    /// If the opcode is outside `1..100` or belongs to a built-in instruction.
+    pub fn register_opcode<T: CustomOpcode + 'static>(&mut self, opcode: i64, custom: T) {
+        assert!(
+            (1..100).contains(&opcode) && Operation::parse(opcode).is_err(),
+            "opcode {} is not available for a custom instruction",
+            opcode
+        );
+        Arc::make_mut(&mut self.opcodes).insert(opcode, Arc::new(custom));
+    }
+
+    /// Adds an instruction that runs a closure. See `register_opcode`.
+    pub fn register_fn<F>(&mut self, opcode: i64, mnemonic: &str, parameters: usize, execute: F)
+    where
+        F: Fn(&mut CustomCall) -> Result<Option<ExitStatus>, ErrorKind> + Send + Sync + 'static,
+    {
+        self.register_opcode(
+            opcode,
+            FnOpcode {
+                mnemonic: mnemonic.to_string(),
+                parameters,
+                execute,
+            },
+        );
+    }
+
+    /// The custom instruction registered for the opcode of `instruction`, if any.
+    pub fn custom_opcode(&self, instruction: i64) -> Option<&dyn CustomOpcode> {
+        self.opcodes
+            .get(&(instruction % 100))
+            .map(|custom| &**custom)
+    }
+
+    /// Executes the custom instruction `custom` at the instruction pointer.
+    pub(crate) fn execute_custom(
+        &mut self,
+        custom: &dyn CustomOpcode,
+    ) -> Result<Option<ExitStatus>, ErrorKind> {
+        let instruction = self.memory.get(self.instruction_ptr);
+        let modes = (0..custom.parameters() as u32)
+            .map(|i| ParameterMode::parse(instruction, i))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut call = CustomCall {
+            machine: self,
+            modes,
+            jumped: false,
+        };
+        let status = custom.execute(&mut call)?;
+        if !call.jumped && status != Some(ExitStatus::Terminated) {
+            self.instruction_ptr += 1 + custom.parameters();
+        }
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{empty, sink};
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Sets its third parameter to the larger of the first two.
+    struct Max;
+
+    impl CustomOpcode for Max {
+        fn mnemonic(&self) -> &str {
+            "MAX"
+        }
+
+        fn parameters(&self) -> usize {
+            3
+        }
+
+        fn execute(&self, call: &mut CustomCall) -> Result<Option<ExitStatus>, ErrorKind> {
+            let value = call.read(0)?.max(call.read(1)?);
+            call.write(2, value)?;
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_custom_opcodes() {
+        // MAX #3, [12], [12]; PRINT [12]; JMP #10; HLT at 10.
+        let program = [120, 3, 12, 12, 21, 12, 122, 10, 0, 0, 99, 0, -5].to_vec();
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let mut comp = Intcode::new(program.clone());
+        comp.register_opcode(20, Max);
+        let log = printed.clone();
+        comp.register_fn(21, "PRINT", 1, move |call| {
+            log.lock().unwrap().push(call.read(0)?);
+            Ok(None)
+        });
+        comp.register_fn(22, "JMP", 1, |call| {
+            let target = call.read(0)?;
+            call.jump(target)?;
+            Ok(None)
+        });
+        assert_eq!(comp.custom_opcode(120).unwrap().mnemonic(), "MAX");
+
+        let mut copy = comp.clone();
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+        assert_eq!(comp.memory[12], 3);
+        assert_eq!(*printed.lock().unwrap(), [3]);
+
+        // Clones keep their opcodes, and statuses from custom instructions are returned.
+        copy.register_fn(21, "OUT2", 1, |call| {
+            Ok(Some(ExitStatus::Output(call.read(0)? * 2)))
+        });
+        let mut output = Vec::new();
+        copy.compute(empty(), &mut output).unwrap();
+        assert_eq!(output, [6]);
+
+        let mut plain = Intcode::new(program);
+        let error = plain.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownOpcode(20));
+    }
+
+    #[test]
+    fn test_custom_undo() {
+        // Adds into [12], sets [13] with a custom instruction, then adds into [12] again.
+        let program = [1101, 1, 1, 12, 23, 13, 1101, 2, 2, 12, 99, 0, 0, 0].to_vec();
+        let mut comp = Intcode::new(program);
+        comp.register_fn(23, "SET7", 1, |call| {
+            call.write(0, 7)?;
+            Ok(None)
+        });
+        comp.enable_undo(None);
+        comp.compute(empty(), sink()).unwrap();
+        while comp.step_back().is_some() {}
+        assert_eq!(comp.instruction_ptr(), 6);
+        assert_eq!(comp.memory[12], 2);
+        assert_eq!(comp.memory[13], 7);
+    }
+
+    #[test]
+    fn test_custom_guards() {
+        // TICK [6] sets [6] to whether the host has counted to 7, and the program waits for it.
+        let program = [21, 6, 1006, 6, 0, 99, 0].to_vec();
+        let ticks = Arc::new(Mutex::new(0));
+        let mut comp = Intcode::new(program);
+        let count = ticks.clone();
+        comp.register_fn(21, "TICK", 1, move |call| {
+            let mut count = count.lock().unwrap();
+            *count += 1;
+            call.write(0, i64::from(*count >= 7))?;
+            Ok(None)
+        });
+        comp.enable_loop_detection();
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+        assert_eq!(*ticks.lock().unwrap(), 7);
+
+        // Writes through a custom instruction to its own cells are self-modification.
+        let mut comp = Intcode::new([21, 0, 99].to_vec());
+        comp.register_fn(21, "ZAP", 1, |call| {
+            call.write(0, 99)?;
+            Ok(None)
+        });
+        comp.detect_self_modification(true);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(
+            error.kind,
+            ErrorKind::SelfModifyingCode {
+                address: 0,
+                writer: 0
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_builtin_opcode() {
+        Intcode::new(Vec::new()).register_opcode(1, Max);
+    }
+}
@@ -0,0 +1,199 @@
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::{Intcode, Operation};
+
+/// Something mapped onto a range of a machine's addresses, such as a clock or a framebuffer.
+/// Instruction operands in that range, in `Position` or `Relative` mode, read from and write to
+/// the device instead of memory.
+pub trait Device: Send {
+    /// The value at `offset` from the start of the device's range.
+    fn read(&mut self, offset: usize) -> i64;
+
+    fn write(&mut self, offset: usize, value: i64);
+}
+
+/// A device and the addresses it is mapped to.
+#[derive(Clone)]
+pub(crate) struct Mapping {
+    range: Range<usize>,
+    device: Arc<Mutex<dyn Device>>,
+}
+
+impl Intcode {
+    /// Maps `device` onto `range`. The caller can keep its own handle to inspect the device,
+    /// and clones of the machine share it.
+    ///
+    /// Instructions are always fetched from plain memory, and undo, snapshots and loop detection
+    /// see only plain memory, not device state. Reading a device counts as reading input, so a
+    /// program polling one is never reported as stuck in a loop.
+    ///
+    /// # Panics
+    ///
+    /// If `range` is empty or overlaps a device already attached.
+    pub fn attach_device<D: Device + 'static>(
+        &mut self,
+        range: Range<usize>,
+        device: Arc<Mutex<D>>,
+    ) {
+        assert!(!range.is_empty(), "device range {:?} is empty", range);
+        assert!(
+            self.devices
+                .iter()
+                .all(|m| m.range.end <= range.start || range.end <= m.range.start),
+            "device range {:?} overlaps another device",
+            range
+        );
+        self.devices.push(Mapping { range, device });
+    }
+
+    /// Removes the device mapped at `address`, returning whether there was one. Its range reads
+    /// from plain memory again.
+    pub fn detach_device(&mut self, address: usize) -> bool {
+        let count = self.devices.len();
+        self.devices.retain(|m| !m.range.contains(&address));
+        self.devices.len() != count
+    }
+
+    /// Reads from the device mapped at `address`, if any.
+    pub(crate) fn device_read(&self, address: usize) -> Option<i64> {
+        let mapping = self.mapping(address)?;
+        let mut device = mapping.device.lock().expect("Device panicked.");
+        Some(device.read(address - mapping.range.start))
+    }
+
+    /// Writes to the device mapped at `address`, returning whether there was one.
+    pub(crate) fn device_write(&self, address: usize, value: i64) -> bool {
+        match self.mapping(address) {
+            Some(mapping) => {
+                let mut device = mapping.device.lock().expect("Device panicked.");
+                device.write(address - mapping.range.start, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether `operation`, about to run, reads an operand from a device. Instructions whose
+    /// operands can't be resolved count as reading one if any device is attached.
+    pub(crate) fn reads_device(&self, operation: Operation) -> bool {
+        if self.devices.is_empty() {
+            return false;
+        }
+        match self.operand_addresses(operation) {
+            Some(operands) => operands.iter().enumerate().any(|(i, (_, address))| {
+                Some(i) != operation.write_parameter()
+                    && address.is_some_and(|a| self.mapping(a).is_some())
+            }),
+            None => true,
+        }
+    }
+
+    fn mapping(&self, address: usize) -> Option<&Mapping> {
+        self.devices.iter().find(|m| m.range.contains(&address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{empty, sink};
+
+    use super::*;
+    use crate::ExitStatus;
+
+    /// Reads as the number of reads before, and ignores writes.
+    #[derive(Default)]
+    struct Counter(i64);
+
+    impl Device for Counter {
+        fn read(&mut self, _: usize) -> i64 {
+            self.0 += 1;
+            self.0 - 1
+        }
+
+        fn write(&mut self, _: usize, _: i64) {}
+    }
+
+    /// Keeps every write.
+    #[derive(Default)]
+    struct Framebuffer(Vec<(usize, i64)>);
+
+    impl Device for Framebuffer {
+        fn read(&mut self, _: usize) -> i64 {
+            0
+        }
+
+        fn write(&mut self, offset: usize, value: i64) {
+            self.0.push((offset, value));
+        }
+    }
+
+    #[test]
+    fn test_devices() {
+        // Adds two reads of the counter at 100, once through each address mode, and writes the
+        // sum to the framebuffer at 200..210, at offset 3.
+        let program = [109, 50, 20201, 50, 100, 153, 99].to_vec();
+        let counter = Arc::new(Mutex::new(Counter(5)));
+        let framebuffer = Arc::new(Mutex::new(Framebuffer::default()));
+        let mut comp = Intcode::new(program);
+        comp.attach_device(100..101, counter.clone());
+        comp.attach_device(200..210, framebuffer.clone());
+        comp.clone().compute(empty(), sink()).unwrap();
+        assert_eq!(framebuffer.lock().unwrap().0, [(3, 5 + 6)]);
+        assert_eq!(counter.lock().unwrap().0, 7);
+
+        // Once detached, the range is plain memory again.
+        assert!(comp.detach_device(205));
+        assert!(!comp.detach_device(205));
+        comp.compute(empty(), sink()).unwrap();
+        assert_eq!(comp.memory[203], 7 + 8);
+        assert_eq!(framebuffer.lock().unwrap().0.len(), 1);
+    }
+
+    #[test]
+    fn test_polling_device() {
+        // Waits for the counter at 100 to reach 5.
+        let program = [1008, 100, 5, 20, 1006, 20, 0, 99].to_vec();
+        let counter = Arc::new(Mutex::new(Counter::default()));
+        let mut comp = Intcode::new(program);
+        comp.attach_device(100..101, counter.clone());
+        comp.enable_loop_detection();
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+        assert_eq!(counter.lock().unwrap().0, 6);
+    }
+
+    #[test]
+    fn test_device_trace() {
+        // Writes 7 to the framebuffer at 200, which leaves memory there as 0.
+        let mut comp = Intcode::new([1101, 3, 4, 200, 99].to_vec());
+        comp.attach_device(200..201, Arc::new(Mutex::new(Framebuffer::default())));
+        comp.enable_trace();
+        comp.compute(empty(), sink()).unwrap();
+        assert_eq!(comp.trace().unwrap().entries[0].write, Some((200, 7)));
+        assert_eq!(comp.memory.get(200), 0);
+        // Operands read from a device are traced as read, and read only once.
+        let counter = Arc::new(Mutex::new(Counter(42)));
+        let mut comp = Intcode::new([1001, 100, 0, 10, 99].to_vec());
+        comp.attach_device(100..101, counter.clone());
+        comp.enable_trace();
+        comp.compute(empty(), sink()).unwrap();
+        let trace = comp.trace().unwrap();
+        assert_eq!(trace.entries[0].operands, [42, 0, 10]);
+        assert_eq!(
+            trace.entries[0].to_string(),
+            "0000: ADD =42, #0, ->[10] | [10] <- 42"
+        );
+        assert_eq!(counter.lock().unwrap().0, 43);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_overlapping_devices() {
+        let mut comp = Intcode::new(Vec::new());
+        comp.attach_device(0..10, Arc::new(Mutex::new(Counter::default())));
+        comp.attach_device(9..11, Arc::new(Mutex::new(Counter::default())));
+    }
+}
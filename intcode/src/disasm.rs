@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::{Memory, Operation, ParameterMode};
+
+/// One line of a disassembly listing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Instruction {
+        address: usize,
+        operation: Operation,
+        parameters: Vec<i64>,
+    },
+    /// A cell that does not decode as an instruction.
+    Data { address: usize, value: i64 },
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        match self {
+            Self::Instruction { address, .. } | Self::Data { address, .. } => *address,
+        }
+    }
+
+    /// The number of cells this line covers.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Instruction { parameters, .. } => parameters.len() + 1,
+            Self::Data { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}: ", self.address())?;
+        match self {
+            Self::Instruction {
+                operation,
+                parameters,
+                ..
+            } => {
+                write!(f, "{}", operation.mnemonic())?;
+                let modes = operation.parameter_modes();
+                for (i, (mode, parameter)) in modes.iter().zip(parameters).enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{}{}", separator, Operand(*mode, *parameter))?;
+                }
+                Ok(())
+            }
+            Self::Data { value, .. } => write!(f, ".data {}", value),
+        }
+    }
+}
+
+/// A parameter formatted according to its mode: `[pos]`, `#imm` or `rb+off`.
+pub struct Operand(pub ParameterMode, pub i64);
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            ParameterMode::Position => write!(f, "[{}]", self.1),
+            ParameterMode::Immediate => write!(f, "#{}", self.1),
+            ParameterMode::Relative if self.1 < 0 => write!(f, "rb{}", self.1),
+            ParameterMode::Relative => write!(f, "rb+{}", self.1),
+        }
+    }
+}
+
+/// Decodes the instruction at `address`, if the cell holds one that fits in the program.
+///
+/// Words that only decode by ignoring digits (such as `100099`) are not instructions, so that
+/// every listing reassembles to the same program.
+pub fn decode(program: &[i64], address: usize) -> Option<Line> {
+    let word = *program.get(address)?;
+    let operation = Operation::parse(word).ok()?;
+    if operation.encode() != word {
+        return None;
+    }
+    let count = operation.parameter_modes().len();
+    let parameters = program.get(address + 1..address + 1 + count)?.to_vec();
+    Some(Line::Instruction {
+        address,
+        operation,
+        parameters,
+    })
+}
+
+/// Decodes the cell at `address` of a running machine's memory, showing it as data if it is not
+/// an instruction.
+pub fn decode_memory(memory: &Memory, address: usize) -> Line {
+    let window = (address..address.saturating_add(4))
+        .map(|a| memory.get(a))
+        .collect::<Vec<_>>();
+    match decode(&window, 0) {
+        Some(Line::Instruction {
+            operation,
+            parameters,
+            ..
+        }) => Line::Instruction {
+            address,
+            operation,
+            parameters,
+        },
+        _ => Line::Data {
+            address,
+            value: memory.get(address),
+        },
+    }
+}
+
+/// A linear sweep over the program, showing each cell that is not a valid instruction as data.
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+    while address < program.len() {
+        let line = decode(program, address).unwrap_or(Line::Data {
+            address,
+            value: program[address],
+        });
+        address += line.size();
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let listing = disassemble(&[109, 1, 21101, 3, -4, -1, 4, 9, 99, 100099, 1107, 1])
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            listing,
+            [
+                "0000: ARB #1",
+                "0002: ADD #3, #-4, rb-1",
+                "0006: OUT [9]",
+                "0008: HLT",
+                "0009: .data 100099",
+                "0010: .data 1107",
+                "0011: .data 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    UnknownOpcode(i64),
+    UnknownParameterMode(i64),
+    ImmediateWrite,
+    InvalidAddress(i64),
+    /// An arithmetic result didn't fit in an `i64` under `Arithmetic::Checked`.
+    Overflow,
+    /// Input was available but was not a valid integer. The machine is left on the `Input`
+    /// instruction and can be resumed with corrected input.
+    MalformedInput(String),
+    Io(io::ErrorKind),
+    /// In strict self-modification mode, the instruction at `writer` wrote to `address`, which
+    /// is part of an instruction that has run or is about to.
+    SelfModifyingCode {
+        address: usize,
+        writer: usize,
+    },
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownOpcode(c) => write!(f, "unrecognized opcode {}", c),
+            Self::UnknownParameterMode(p) => write!(f, "unrecognized parameter mode {}", p),
+            Self::ImmediateWrite => write!(f, "cannot write with immediate mode"),
+            Self::InvalidAddress(a) => write!(f, "invalid address {}", a),
+            Self::Overflow => write!(f, "arithmetic overflow"),
+            Self::MalformedInput(token) => write!(f, "malformed input {:?}", token),
+            Self::Io(e) => write!(f, "I/O error: {:?}", e),
+            Self::SelfModifyingCode { address, writer } => write!(
+                f,
+                "instruction at {} modified code at address {}",
+                writer, address
+            ),
+        }
+    }
+}
+
+/// An error raised while executing an instruction, along with where it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntcodeError {
+    pub kind: ErrorKind,
+    pub instruction_ptr: usize,
+    pub instruction: i64,
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at instruction {} (word {})",
+            self.kind, self.instruction_ptr, self.instruction
+        )
+    }
+}
+
+impl Error for IntcodeError {}
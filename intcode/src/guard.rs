@@ -0,0 +1,224 @@
+use crate::{ExitStatus, Intcode, Memory};
+
+/// Proves a program is stuck by finding a repeat of the whole machine state, using Brent's
+/// cycle detection so only one earlier state needs to be kept.
+///
+/// Memory is compared cell by cell only when the instruction pointer, relative base and a
+/// running hash of memory all match, so a reported loop is never a hash collision.
+///
+/// The hash is kept up to date by the machine's own writes. Memory edited from outside, through
+/// `Memory::set`, is noticed at the next check and treated like input: the hash is recomputed
+/// and the search starts over.
+#[derive(Clone)]
+pub(crate) struct LoopDetector {
+    /// The period of the loop once one has been found.
+    found: Option<u64>,
+    memory_hash: u64,
+    /// `Memory::writes` as of the last write the detector saw.
+    writes: u64,
+    saved: Option<SavedState>,
+    power: u64,
+    steps: u64,
+}
+
+#[derive(Clone)]
+struct SavedState {
+    memory: Memory,
+    memory_hash: u64,
+    instruction_ptr: usize,
+    relative_base: i64,
+}
+
+impl LoopDetector {
+    fn new(memory: &Memory) -> Self {
+        LoopDetector {
+            found: None,
+            memory_hash: hash(memory),
+            writes: memory.writes(),
+            saved: None,
+            power: 1,
+            steps: 0,
+        }
+    }
+
+    /// Accounts for a write the machine is about to make through `Memory::set`.
+    pub(crate) fn update(&mut self, address: usize, old: i64, new: i64) {
+        self.writes = self.writes.wrapping_add(1);
+        self.memory_hash = self
+            .memory_hash
+            .wrapping_sub(mix(address, old))
+            .wrapping_add(mix(address, new));
+    }
+
+    /// Returns the period of the loop if the machine is back in the saved state.
+    fn check(&mut self, machine: &Intcode, consumed_input: bool) -> Option<u64> {
+        let edited = self.writes != machine.memory.writes();
+        if edited {
+            self.memory_hash = hash(&machine.memory);
+            self.writes = machine.memory.writes();
+        }
+        if !consumed_input && !edited {
+            self.steps += 1;
+            if let Some(saved) = &self.saved {
+                if saved.instruction_ptr == machine.instruction_ptr
+                    && saved.relative_base == machine.relative_base
+                    && saved.memory_hash == self.memory_hash
+                    && saved.memory == machine.memory
+                {
+                    return Some(self.steps);
+                }
+            }
+            if self.saved.is_some() && self.steps < self.power {
+                return None;
+            }
+            self.power *= 2;
+        } else {
+            // The state after reading input or an outside edit depends on it, so start over.
+            self.power = 1;
+        }
+        self.steps = 0;
+        self.saved = Some(SavedState {
+            memory: machine.memory.clone(),
+            memory_hash: self.memory_hash,
+            instruction_ptr: machine.instruction_ptr,
+            relative_base: machine.relative_base,
+        });
+        None
+    }
+}
+
+fn hash(memory: &Memory) -> u64 {
+    memory.cells().fold(0, |hash, (address, value)| {
+        hash.wrapping_add(mix(address, value))
+    })
+}
+
+/// Hashes one memory cell. Zero cells hash to zero, so unwritten and zeroed cells agree.
+fn mix(address: usize, value: i64) -> u64 {
+    if value == 0 {
+        return 0;
+    }
+    let mut x = (address as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ value as u64;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+impl Intcode {
+    /// Limits how many more instructions may run. Once it's spent, execution stops with
+    /// `ExitStatus::BudgetExhausted` and resumes after the budget is raised again.
+    pub fn set_budget(&mut self, budget: Option<u64>) {
+        self.budget = budget;
+    }
+
+    pub fn budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    /// Stops execution with `ExitStatus::InfiniteLoop` if the machine returns to an earlier
+    /// state without reading input or a device in between.
+    pub fn enable_loop_detection(&mut self) {
+        self.loop_detector = Some(LoopDetector::new(&self.memory));
+    }
+
+    pub fn disable_loop_detection(&mut self) {
+        self.loop_detector = None;
+    }
+
+    /// Why the machine may not run another instruction, if the budget is spent or it is stuck
+    /// in a loop.
+    pub(crate) fn halt_reason(&self) -> Option<ExitStatus> {
+        if let Some(period) = self.loop_detector.as_ref().and_then(|d| d.found) {
+            return Some(ExitStatus::InfiniteLoop { period });
+        }
+        match self.budget {
+            Some(0) => Some(ExitStatus::BudgetExhausted),
+            _ => None,
+        }
+    }
+
+    /// Charges one instruction against the budget and runs the loop detector after it has
+    /// executed.
+    pub(crate) fn guard(&mut self, consumed_input: bool, status: Option<ExitStatus>) {
+        if let Some(budget) = self.budget.as_mut() {
+            *budget = budget.saturating_sub(1);
+        }
+        if status == Some(ExitStatus::Terminated) {
+            return;
+        }
+        if let Some(mut detector) = self.loop_detector.take() {
+            detector.found = detector.check(self, consumed_input);
+            self.loop_detector = Some(detector);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::{empty, sink};
+
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        // Counts 10 down to 0 in cell 9.
+        let program = [1001, 9, -1, 9, 1005, 9, 0, 99, 0, 10].to_vec();
+        let mut comp = Intcode::new(program);
+        comp.set_budget(Some(5));
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::BudgetExhausted
+        );
+        assert_eq!(comp.budget(), Some(0));
+        assert_eq!(comp.memory[9], 7);
+
+        comp.set_budget(None);
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+        assert_eq!(comp.memory[9], 0);
+    }
+
+    #[test]
+    fn test_loop_detection() {
+        // Toggles cell 11 between 0 and 1 forever.
+        let mut comp = Intcode::new([1002, 11, -1, 11, 1001, 11, 1, 11, 1105, 1, 0, 0].to_vec());
+        comp.enable_loop_detection();
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::InfiniteLoop { period: 6 }
+        );
+
+        // Reads input until it gets a zero, so it only loops while input keeps coming.
+        let mut comp = Intcode::new([3, 7, 1005, 7, 0, 99].to_vec());
+        comp.enable_loop_detection();
+        let mut input = VecDeque::from([1, 1, 1, 1, 1, 0]);
+        assert_eq!(
+            comp.compute(&mut input, sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+
+        let mut comp = Intcode::new([1001, 9, -1, 9, 1005, 9, 0, 99, 0, 10].to_vec());
+        comp.enable_loop_detection();
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+        // Edits from outside are seen, so the hash stays right: stopping the countdown from
+        // outside leaves it spinning.
+        let mut comp = Intcode::new([1001, 9, -1, 9, 1005, 9, 0, 99, 0, 10].to_vec());
+        comp.enable_loop_detection();
+        comp.set_budget(Some(3));
+        comp.compute(empty(), sink()).unwrap();
+        comp.memory.set(2, 0);
+        comp.set_budget(None);
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::InfiniteLoop { period: 2 }
+        );
+        let detector = comp.loop_detector.as_ref().unwrap();
+        assert_eq!(detector.memory_hash, hash(&comp.memory));
+    }
+}
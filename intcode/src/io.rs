@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+use crate::ErrorKind;
+
+/// A source of values for the `Input` instruction. `W` is the machine's word type.
+pub trait IntcodeInput<W = i64> {
+    /// The next value, or `None` if no input is available yet.
+    fn read(&mut self) -> Result<Option<W>, ErrorKind>;
+}
+
+/// A destination for values from the `Output` instruction. `W` is the machine's word type.
+pub trait IntcodeOutput<W = i64> {
+    fn write(&mut self, value: W) -> Result<(), ErrorKind>;
+}
+
+impl<W, T: IntcodeInput<W> + ?Sized> IntcodeInput<W> for &mut T {
+    fn read(&mut self) -> Result<Option<W>, ErrorKind> {
+        (**self).read()
+    }
+}
+
+impl<W, T: IntcodeOutput<W> + ?Sized> IntcodeOutput<W> for &mut T {
+    fn write(&mut self, value: W) -> Result<(), ErrorKind> {
+        (**self).write(value)
+    }
+}
+
+impl<W> IntcodeInput<W> for VecDeque<W> {
+    fn read(&mut self) -> Result<Option<W>, ErrorKind> {
+        Ok(self.pop_front())
+    }
+}
+
+impl<W> IntcodeOutput<W> for VecDeque<W> {
+    fn write(&mut self, value: W) -> Result<(), ErrorKind> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+impl<W> IntcodeOutput<W> for Vec<W> {
+    fn write(&mut self, value: W) -> Result<(), ErrorKind> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+/// Blocks until a value arrives. Input is exhausted once every sender has been dropped.
+impl<W> IntcodeInput<W> for Receiver<W> {
+    fn read(&mut self) -> Result<Option<W>, ErrorKind> {
+        Ok(self.recv().ok())
+    }
+}
+
+impl<W> IntcodeOutput<W> for Sender<W> {
+    fn write(&mut self, value: W) -> Result<(), ErrorKind> {
+        self.send(value)
+            .map_err(|_| ErrorKind::Io(io::ErrorKind::BrokenPipe))
+    }
+}
+
+impl<W> IntcodeInput<W> for io::Empty {
+    fn read(&mut self) -> Result<Option<W>, ErrorKind> {
+        Ok(None)
+    }
+}
+
+impl<W> IntcodeOutput<W> for io::Sink {
+    fn write(&mut self, _value: W) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+}
+
+/// Input drawn from any iterator of values.
+pub struct IterInput<I>(pub I);
+
+impl<W, I: Iterator<Item = W>> IntcodeInput<W> for IterInput<I> {
+    fn read(&mut self) -> Result<Option<W>, ErrorKind> {
+        Ok(self.0.next())
+    }
+}
+
+/// Input produced on demand by a closure.
+pub struct FnInput<F>(pub F);
+
+impl<W, F: FnMut() -> Option<W>> IntcodeInput<W> for FnInput<F> {
+    fn read(&mut self) -> Result<Option<W>, ErrorKind> {
+        Ok((self.0)())
+    }
+}
+
+/// Output handed to a closure.
+pub struct FnOutput<F>(pub F);
+
+impl<W, F: FnMut(W)> IntcodeOutput<W> for FnOutput<F> {
+    fn write(&mut self, value: W) -> Result<(), ErrorKind> {
+        (self.0)(value);
+        Ok(())
+    }
+}
+
+/// Input read as one integer per line of text. End of stream means no input is available yet;
+/// a line that is not an integer is reported as `MalformedInput`.
+pub struct TextInput<R>(pub R);
+
+impl<R: BufRead> IntcodeInput for TextInput<R> {
+    fn read(&mut self) -> Result<Option<i64>, ErrorKind> {
+        let mut line = String::new();
+        let read = self
+            .0
+            .read_line(&mut line)
+            .map_err(|e| ErrorKind::Io(e.kind()))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        let token = line.trim();
+        match token.parse::<i64>() {
+            Ok(i) => Ok(Some(i)),
+            Err(_) => Err(ErrorKind::MalformedInput(token.to_string())),
+        }
+    }
+}
+
+/// Output written as one integer per line of text.
+pub struct TextOutput<W>(pub W);
+
+impl<W: Write> IntcodeOutput for TextOutput<W> {
+    fn write(&mut self, value: i64) -> Result<(), ErrorKind> {
+        writeln!(self.0, "{}", value).map_err(|e| ErrorKind::Io(e.kind()))
+    }
+}
+
+/// Input read as lines of ASCII text. Each line is given as its character codes followed by a
+/// newline (10). End of stream means no input is available yet.
+pub struct AsciiInput<R> {
+    reader: R,
+    pending: VecDeque<i64>,
+}
+
+impl<R: BufRead> AsciiInput<R> {
+    pub fn new(reader: R) -> Self {
+        AsciiInput {
+            reader,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: BufRead> IntcodeInput for AsciiInput<R> {
+    fn read(&mut self) -> Result<Option<i64>, ErrorKind> {
+        if self.pending.is_empty() {
+            let mut line = String::new();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|e| ErrorKind::Io(e.kind()))?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.pending = ascii_line(&line);
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+/// The character codes of `line` without its line ending, followed by a newline.
+pub(crate) fn ascii_line(line: &str) -> VecDeque<i64> {
+    line.trim_end_matches(&['\r', '\n'][..])
+        .bytes()
+        .map(i64::from)
+        .chain(std::iter::once(10))
+        .collect()
+}
+
+/// Output written as ASCII text. Values outside the ASCII range are written as integers on
+/// their own line and kept in `raw`.
+pub struct AsciiOutput<W> {
+    pub writer: W,
+    pub raw: Vec<i64>,
+}
+
+impl<W: Write> AsciiOutput<W> {
+    pub fn new(writer: W) -> Self {
+        AsciiOutput {
+            writer,
+            raw: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> IntcodeOutput for AsciiOutput<W> {
+    fn write(&mut self, value: i64) -> Result<(), ErrorKind> {
+        let written = match value {
+            0..=127 => self.writer.write_all(&[value as u8]),
+            _ => {
+                self.raw.push(value);
+                writeln!(self.writer, "{}", value)
+            }
+        };
+        written.map_err(|e| ErrorKind::Io(e.kind()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn test_adapters() {
+        let mut input = TextInput(&b"12\n-3\n"[..]);
+        assert_eq!(input.read(), Ok(Some(12)));
+        assert_eq!(input.read(), Ok(Some(-3)));
+        assert_eq!(input.read(), Ok(None));
+
+        let mut input = TextInput(&b"8x\n\n7"[..]);
+        assert_eq!(
+            input.read(),
+            Err(ErrorKind::MalformedInput("8x".to_string()))
+        );
+        assert_eq!(input.read(), Err(ErrorKind::MalformedInput("".to_string())));
+        assert_eq!(input.read(), Ok(Some(7)));
+        assert_eq!(input.read(), Ok(None));
+
+        let mut output = TextOutput(Vec::new());
+        output.write(5).unwrap();
+        output.write(-6).unwrap();
+        assert_eq!(output.0, b"5\n-6\n");
+
+        let mut input = IterInput(1..3);
+        assert_eq!(input.read(), Ok(Some(1)));
+        assert_eq!(input.read(), Ok(Some(2)));
+        assert_eq!(input.read(), Ok(None));
+
+        let mut seen = Vec::new();
+        FnOutput(|v| seen.push(v)).write(4).unwrap();
+        assert_eq!(seen, [4]);
+
+        let mut input = AsciiInput::new(&b"Hi\r\n\n"[..]);
+        let codes = (0..5).map(|_| input.read().unwrap()).collect::<Vec<_>>();
+        assert_eq!(codes, [Some(72), Some(105), Some(10), Some(10), None]);
+
+        let mut output = AsciiOutput::new(Vec::new());
+        for value in [79, 75, 10, 19_349_530].iter() {
+            output.write(*value).unwrap();
+        }
+        assert_eq!(output.writer, b"OK\n19349530\n");
+        assert_eq!(output.raw, [19_349_530]);
+
+        let (mut sender, mut receiver) = channel();
+        sender.write(9).unwrap();
+        drop(sender);
+        assert_eq!(receiver.read(), Ok(Some(9)));
+        assert_eq!(receiver.read(), Ok(None));
+    }
+}
@@ -1,218 +1,385 @@
-use std::io::prelude::*;
+use std::collections::VecDeque;
+
+mod arithmetic;
+pub mod asm;
+mod asynchronous;
+mod console;
+mod custom;
+mod device;
+pub mod disasm;
+mod error;
+mod guard;
+mod io;
+mod load;
+mod memory;
+mod network;
+mod operation;
+mod processor;
+mod profile;
+mod search;
+mod selfmod;
+mod snapshot;
+pub mod topology;
+mod trace;
+mod undo;
+#[cfg(feature = "bigint")]
+mod wide;
+
+pub use arithmetic::Arithmetic;
+pub use asynchronous::{
+    block_on, join_all, pipe, AsyncInput, AsyncOutput, PipeReceiver, PipeSender,
+};
+pub use console::{Console, ConsoleMode};
+use custom::CustomOpcodes;
+pub use custom::{CustomCall, CustomOpcode};
+pub use device::Device;
+use device::Mapping;
+pub use error::{ErrorKind, IntcodeError};
+use guard::LoopDetector;
+pub use io::{
+    AsciiInput, AsciiOutput, FnInput, FnOutput, IntcodeInput, IntcodeOutput, IterInput, TextInput,
+    TextOutput,
+};
+pub use load::{load_program, load_program_stdin, parse_program, LoadError};
+pub use memory::Memory;
+pub use network::{Network, NetworkError, NetworkStatus, Packet, Route};
+pub use operation::{Operation, ParameterMode};
+use processor::Processor;
+pub use processor::Word;
+pub use profile::Profile;
+pub use search::{search, search_by, Candidate};
+use selfmod::SelfModDetector;
+pub use selfmod::SelfModification;
+pub use snapshot::Snapshot;
+pub use trace::{Trace, TraceEntry};
+use undo::{Chain, UndoLog};
+#[cfg(feature = "bigint")]
+pub use wide::WideIntcode;
 
 #[derive(Clone)]
 pub struct Intcode {
-    pub program: Vec<i64>,
+    pub memory: Memory,
     instruction_ptr: usize,
     relative_base: i64,
+    trace: Option<Trace>,
+    profile: Option<Profile>,
+    budget: Option<u64>,
+    loop_detector: Option<LoopDetector>,
+    self_mod: Option<SelfModDetector>,
+    undo: Option<UndoLog>,
+    unread: VecDeque<i64>,
+    opcodes: CustomOpcodes,
+    arithmetic: Arithmetic,
+    devices: Vec<Mapping>,
+    /// The address and value of the last write, which may have gone to a device.
+    last_write: Option<(usize, i64)>,
+    /// The operands the running instruction has read, by parameter index, while tracing.
+    operands_read: Vec<(usize, i64)>,
 }
 
 impl Intcode {
-    pub fn new(mut program: Vec<i64>) -> Self {
-        program.extend_from_slice(&vec![0; 2048 - program.len()]);
+    pub fn new(program: Vec<i64>) -> Self {
+        Self::with_memory(Memory::new(program))
+    }
+
+    /// A machine whose memory is addressable only below `limit`.
+    pub fn with_memory_limit(program: Vec<i64>, limit: usize) -> Self {
+        Self::with_memory(Memory::with_limit(program, limit))
+    }
+
+    pub fn with_memory(memory: Memory) -> Self {
         Intcode {
-            program,
+            memory,
             instruction_ptr: 0,
             relative_base: 0,
+            trace: None,
+            profile: None,
+            budget: None,
+            loop_detector: None,
+            self_mod: None,
+            undo: None,
+            unread: VecDeque::new(),
+            opcodes: CustomOpcodes::default(),
+            arithmetic: Arithmetic::default(),
+            devices: Vec::new(),
+            last_write: None,
+            operands_read: Vec::new(),
         }
     }
-}
-
-enum ParameterMode {
-    Position,
-    Immediate,
-    Relative,
-}
 
-enum Operation {
-    Addition(ParameterMode, ParameterMode, ParameterMode),
-    Multiplication(ParameterMode, ParameterMode, ParameterMode),
-    Input(ParameterMode),
-    Output(ParameterMode),
-    JumpTrue(ParameterMode, ParameterMode),
-    JumpFalse(ParameterMode, ParameterMode),
-    Less(ParameterMode, ParameterMode, ParameterMode),
-    Equal(ParameterMode, ParameterMode, ParameterMode),
-    ChangeRelativeBase(ParameterMode),
-    Terminate,
-}
+    pub fn instruction_ptr(&self) -> usize {
+        self.instruction_ptr
+    }
 
-impl Operation {
-    fn advance(&self) -> usize {
-        match self {
-            Self::Addition(_, _, _) => 4,
-            Self::Multiplication(_, _, _) => 4,
-            Self::Input(_) => 2,
-            Self::Output(_) => 2,
-            Self::JumpTrue(_, _) => 3,
-            Self::JumpFalse(_, _) => 3,
-            Self::Less(_, _, _) => 4,
-            Self::Equal(_, _, _) => 4,
-            Self::ChangeRelativeBase(_) => 2,
-            Self::Terminate => 0,
-        }
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
     }
 }
 
-#[derive(PartialEq)]
-pub enum ExitStatus {
+/// Why a machine stopped. `W` is the type of its memory cells.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitStatus<W = i64> {
     AwaitingInput,
     Terminated,
+    /// A value from the `Output` instruction. Only returned by `step` and `run_until_output`;
+    /// `compute` passes outputs to its sink instead.
+    Output(W),
+    /// The instruction budget ran out. Raising it with `set_budget` resumes the program.
+    BudgetExhausted,
+    /// Loop detection found the machine back in an earlier state, so it will never halt.
+    InfiniteLoop {
+        period: u64,
+    },
 }
 
 impl Intcode {
-    pub fn compute<R, W>(&mut self, mut reader: R, mut writer: W) -> ExitStatus
+    pub fn compute<I, O>(&mut self, mut input: I, mut output: O) -> Result<ExitStatus, IntcodeError>
     where
-        R: BufRead,
-        W: Write,
+        I: IntcodeInput,
+        O: IntcodeOutput,
     {
         loop {
-            let operation = Self::parse_operation(self.program[self.instruction_ptr]);
-            let mut advance = operation.advance();
-            match operation {
-                Operation::Addition(p1, p2, p3) => {
-                    self.write(
-                        3,
-                        self.value_from_parameter(1, p1) + self.value_from_parameter(2, p2),
-                        p3,
-                    );
-                }
-                Operation::Multiplication(p1, p2, p3) => {
-                    self.write(
-                        3,
-                        self.value_from_parameter(1, p1) * self.value_from_parameter(2, p2),
-                        p3,
-                    );
-                }
-                Operation::Input(p1) => {
-                    let mut op_input = String::new();
-                    reader.read_line(&mut op_input).expect("Unable to read");
-                    let op_input = op_input.trim().parse::<i64>();
-                    match op_input {
-                        Ok(i) => self.write(1, i, p1),
-                        Err(_) => return ExitStatus::AwaitingInput,
-                    }
-                }
-                Operation::Output(p1) => {
-                    writeln!(&mut writer, "{}", self.value_from_parameter(1, p1))
-                        .expect("Unable to write");
+            let instruction_ptr = self.instruction_ptr;
+            match self.step(&mut input)? {
+                Some(ExitStatus::Output(value)) => {
+                    output
+                        .write(value)
+                        .map_err(|e| self.error_at(instruction_ptr, e))?;
                 }
-                Operation::JumpTrue(p1, p2) => {
-                    if self.value_from_parameter(1, p1) != 0 {
-                        advance = 0;
-                        self.instruction_ptr = self.value_from_parameter(2, p2) as usize;
-                    }
-                }
-                Operation::JumpFalse(p1, p2) => {
-                    if self.value_from_parameter(1, p1) == 0 {
-                        advance = 0;
-                        self.instruction_ptr = self.value_from_parameter(2, p2) as usize;
-                    }
-                }
-                Operation::Less(p1, p2, p3) => {
-                    self.write(
-                        3,
-                        match self.value_from_parameter(1, p1) < self.value_from_parameter(2, p2) {
-                            true => 1,
-                            false => 0,
-                        },
-                        p3,
-                    );
-                }
-                Operation::Equal(p1, p2, p3) => {
-                    self.write(
-                        3,
-                        match self.value_from_parameter(1, p1) == self.value_from_parameter(2, p2) {
-                            true => 1,
-                            false => 0,
-                        },
-                        p3,
-                    );
-                }
-                Operation::ChangeRelativeBase(p1) => {
-                    self.relative_base += self.value_from_parameter(1, p1);
-                }
-                Operation::Terminate => return ExitStatus::Terminated,
+                Some(status) => return Ok(status),
+                None => {}
             }
-            self.instruction_ptr += advance;
         }
     }
 
-    fn write(&mut self, offset: usize, value: i64, mode: ParameterMode) {
-        let address = match mode {
-            ParameterMode::Position => self.program[self.instruction_ptr + offset] as usize,
-            ParameterMode::Relative => {
-                (self.program[self.instruction_ptr + offset] + self.relative_base) as usize
+    /// Runs until the program outputs a value, needs input it doesn't have, or terminates.
+    pub fn run_until_output<I>(&mut self, mut input: I) -> Result<ExitStatus, IntcodeError>
+    where
+        I: IntcodeInput,
+    {
+        loop {
+            if let Some(status) = self.step(&mut input)? {
+                return Ok(status);
             }
-            _ => unreachable!("Cannot write with immediate mode."),
+        }
+    }
+
+    /// Executes exactly one instruction. Returns `None` if the machine is still running, or the
+    /// status if it output a value, needs input, or terminated.
+    pub fn step<I>(&mut self, mut input: I) -> Result<Option<ExitStatus>, IntcodeError>
+    where
+        I: IntcodeInput,
+    {
+        if let Some(status) = self.halt_reason() {
+            return Ok(Some(status));
+        }
+        let instruction_ptr = self
+            .address(self.instruction_ptr as i64)
+            .map_err(|e| self.error(e))?;
+        let operation = match self.memory.decode(instruction_ptr) {
+            Ok(operation) => operation,
+            Err(ErrorKind::UnknownOpcode(opcode)) if self.opcodes.contains_key(&opcode) => {
+                let custom = self.opcodes[&opcode].clone();
+                self.check_execute(1 + custom.parameters())
+                    .map_err(|e| self.error(e))?;
+                let status = self.execute_custom(&*custom).map_err(|e| self.error(e))?;
+                self.clear_undo();
+                // The instruction may have read host state, so it can't be part of a proven loop.
+                self.guard(true, status);
+                if status.is_none() {
+                    return Ok(self.halt_reason());
+                }
+                return Ok(status);
+            }
+            Err(e) => return Err(self.error(e)),
+        };
+        self.check_execute(operation.advance().max(1))
+            .map_err(|e| self.error(e))?;
+        self.operands_read.clear();
+        let entry = match self.trace {
+            Some(_) => self.trace_entry(self.memory.get(instruction_ptr), operation),
+            None => None,
+        };
+        let operands = match self.profile {
+            Some(_) => self.operand_addresses(operation),
+            None => None,
+        };
+        let undo = match self.undo {
+            Some(_) => self.undo_record(operation),
+            None => None,
         };
-        self.program[address] = value;
-    }
-
-    fn parse_operation(instruction: i64) -> Operation {
-        let op_code = instruction % 100;
-        match op_code {
-            1 => Operation::Addition(
-                Self::parse_parameter_mode(instruction, 0),
-                Self::parse_parameter_mode(instruction, 1),
-                Self::parse_parameter_mode(instruction, 2),
-            ),
-            2 => Operation::Multiplication(
-                Self::parse_parameter_mode(instruction, 0),
-                Self::parse_parameter_mode(instruction, 1),
-                Self::parse_parameter_mode(instruction, 2),
-            ),
-            3 => Operation::Input(Self::parse_parameter_mode(instruction, 0)),
-            4 => Operation::Output(Self::parse_parameter_mode(instruction, 0)),
-            5 => Operation::JumpTrue(
-                Self::parse_parameter_mode(instruction, 0),
-                Self::parse_parameter_mode(instruction, 1),
-            ),
-            6 => Operation::JumpFalse(
-                Self::parse_parameter_mode(instruction, 0),
-                Self::parse_parameter_mode(instruction, 1),
-            ),
-            7 => Operation::Less(
-                Self::parse_parameter_mode(instruction, 0),
-                Self::parse_parameter_mode(instruction, 1),
-                Self::parse_parameter_mode(instruction, 2),
-            ),
-            8 => Operation::Equal(
-                Self::parse_parameter_mode(instruction, 0),
-                Self::parse_parameter_mode(instruction, 1),
-                Self::parse_parameter_mode(instruction, 2),
-            ),
-            9 => Operation::ChangeRelativeBase(Self::parse_parameter_mode(instruction, 0)),
-            99 => Operation::Terminate,
-            c => panic!("Unrecognized instruction: {}", c),
+        let reads_device = self.reads_device(operation);
+        let status = match self.unread.is_empty() {
+            true => processor::execute(self, operation, &mut input),
+            false => {
+                let mut unread = std::mem::take(&mut self.unread);
+                let status =
+                    processor::execute(self, operation, &mut Chain(&mut unread, &mut input));
+                self.unread = unread;
+                status
+            }
+        }
+        .map_err(|e| self.error(e))?;
+        if status != Some(ExitStatus::AwaitingInput) {
+            if let Some(undo) = undo {
+                self.record_undo(undo);
+            }
+            if let Some(entry) = entry {
+                self.record_trace(entry);
+            }
+            if let Some(operands) = operands {
+                self.record_profile(instruction_ptr, operation, &operands);
+            }
+            self.guard(
+                matches!(operation, Operation::Input(_)) || reads_device,
+                status,
+            );
+            if status.is_none() {
+                return Ok(self.halt_reason());
+            }
         }
+        Ok(status)
     }
 
-    fn parse_parameter_mode(instruction: i64, position: u32) -> ParameterMode {
-        match instruction / (10i64.pow(position + 2)) % 10 {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::Relative,
-            p => panic!("Unrecognized parameter mode: {}", p),
+    fn error(&self, kind: ErrorKind) -> IntcodeError {
+        self.error_at(self.instruction_ptr, kind)
+    }
+
+    fn error_at(&self, instruction_ptr: usize, kind: ErrorKind) -> IntcodeError {
+        IntcodeError {
+            kind,
+            instruction_ptr,
+            instruction: self.memory.get(instruction_ptr),
         }
     }
 
-    fn value_from_parameter(&self, offset: usize, mode: ParameterMode) -> i64 {
-        match mode {
-            ParameterMode::Position => {
-                self.program[self.program[self.instruction_ptr + offset] as usize]
-            }
-            ParameterMode::Immediate => self.program[self.instruction_ptr + offset],
-            ParameterMode::Relative => {
-                self.program
-                    [(self.program[self.instruction_ptr + offset] + self.relative_base) as usize]
+    fn address(&self, address: i64) -> Result<usize, ErrorKind> {
+        if address < 0 || !self.memory.contains(address as usize) {
+            return Err(ErrorKind::InvalidAddress(address));
+        }
+        Ok(address as usize)
+    }
+
+    /// The address a `Relative` parameter refers to. Adding the relative base follows the
+    /// arithmetic policy, so under `Arithmetic::Checked` an overflow is an error.
+    fn relative_address(&self, parameter: i64) -> Result<usize, ErrorKind> {
+        self.address(self.arithmetic.add(parameter, self.relative_base)?)
+    }
+
+    /// Each parameter of the instruction about to run, with the address it refers to unless it
+    /// is immediate. Returns `None` if an address is invalid.
+    pub(crate) fn operand_addresses(
+        &self,
+        operation: Operation,
+    ) -> Option<Vec<(i64, Option<usize>)>> {
+        operation
+            .parameter_modes()
+            .iter()
+            .enumerate()
+            .map(|(i, mode)| {
+                let parameter = self.read((self.instruction_ptr + i + 1) as i64).ok()?;
+                let address = match mode {
+                    ParameterMode::Immediate => return Some((parameter, None)),
+                    ParameterMode::Position => self.address(parameter),
+                    ParameterMode::Relative => self.relative_address(parameter),
+                };
+                Some((parameter, Some(address.ok()?)))
+            })
+            .collect()
+    }
+
+    /// The address the instruction at the instruction pointer will write to, if it writes and
+    /// can be decoded.
+    pub fn write_address(&self) -> Option<usize> {
+        let operation = Operation::parse(self.memory.get(self.instruction_ptr)).ok()?;
+        self.operand_addresses(operation)?[operation.write_parameter()?].1
+    }
+
+    fn read(&self, address: i64) -> Result<i64, ErrorKind> {
+        Ok(self.memory.get(self.address(address)?))
+    }
+
+    /// Reads an operand, from the device mapped at `address` if there is one.
+    fn load(&self, address: usize) -> i64 {
+        match self.device_read(address) {
+            Some(value) => value,
+            None => self.memory.get(address),
+        }
+    }
+
+    fn write(&mut self, offset: usize, value: i64, mode: ParameterMode) -> Result<(), ErrorKind> {
+        let parameter = self.read((self.instruction_ptr + offset) as i64)?;
+        let address = match mode {
+            ParameterMode::Position => self.address(parameter)?,
+            ParameterMode::Relative => self.relative_address(parameter)?,
+            ParameterMode::Immediate => return Err(ErrorKind::ImmediateWrite),
+        };
+        if !self.device_write(address, value) {
+            self.check_write(address, value)?;
+            if let Some(detector) = self.loop_detector.as_mut() {
+                detector.update(address, self.memory.get(address), value);
             }
+            self.memory.set(address, value);
+        }
+        self.last_write = Some((address, value));
+        Ok(())
+    }
+
+    fn value_from_parameter(&self, offset: usize, mode: ParameterMode) -> Result<i64, ErrorKind> {
+        let parameter = self.read((self.instruction_ptr + offset) as i64)?;
+        match mode {
+            ParameterMode::Position => Ok(self.load(self.address(parameter)?)),
+            ParameterMode::Immediate => Ok(parameter),
+            ParameterMode::Relative => Ok(self.load(self.relative_address(parameter)?)),
         }
     }
 }
 
+impl Processor for Intcode {
+    type Word = i64;
+
+    fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    fn parameter(&mut self, offset: usize, mode: ParameterMode) -> Result<i64, ErrorKind> {
+        let value = self.value_from_parameter(offset, mode)?;
+        if self.trace.is_some() {
+            self.operands_read.push((offset - 1, value));
+        }
+        Ok(value)
+    }
+
+    fn write_parameter(
+        &mut self,
+        offset: usize,
+        value: i64,
+        mode: ParameterMode,
+    ) -> Result<(), ErrorKind> {
+        self.write(offset, value, mode)
+    }
+
+    fn jump(&mut self, target: i64) -> Result<(), ErrorKind> {
+        self.instruction_ptr = self.address(target)?;
+        Ok(())
+    }
+
+    fn advance(&mut self, by: usize) {
+        self.instruction_ptr += by;
+    }
+
+    fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::VecDeque;
     use std::io::{empty, sink};
 
     use super::*;
@@ -220,108 +387,108 @@ mod tests {
     #[test]
     fn test_simple() {
         let mut computer = Intcode::new([1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50].to_vec());
-        computer.compute(empty(), sink());
+        computer.compute(empty(), sink()).unwrap();
         assert_eq!(
-            computer.program,
+            computer.memory.to_vec(),
             [3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50].to_vec()
         );
 
         let mut computer = Intcode::new([1, 0, 0, 0, 99].to_vec());
-        computer.compute(empty(), sink());
-        assert_eq!(computer.program, [2, 0, 0, 0, 99]);
+        computer.compute(empty(), sink()).unwrap();
+        assert_eq!(computer.memory.to_vec(), [2, 0, 0, 0, 99]);
 
         let mut computer = Intcode::new([2, 3, 0, 3, 99].to_vec());
-        computer.compute(empty(), sink());
-        assert_eq!(computer.program, [2, 3, 0, 6, 99]);
+        computer.compute(empty(), sink()).unwrap();
+        assert_eq!(computer.memory.to_vec(), [2, 3, 0, 6, 99]);
 
         let mut computer = Intcode::new([2, 4, 4, 5, 99, 0].to_vec());
-        computer.compute(empty(), sink());
-        assert_eq!(computer.program, [2, 4, 4, 5, 99, 9801]);
+        computer.compute(empty(), sink()).unwrap();
+        assert_eq!(computer.memory.to_vec(), [2, 4, 4, 5, 99, 9801]);
 
         let mut computer = Intcode::new([1, 1, 1, 4, 99, 5, 6, 0, 99].to_vec());
-        computer.compute(empty(), sink());
-        assert_eq!(computer.program, [30, 1, 1, 4, 2, 5, 6, 0, 99]);
+        computer.compute(empty(), sink()).unwrap();
+        assert_eq!(computer.memory.to_vec(), [30, 1, 1, 4, 2, 5, 6, 0, 99]);
     }
 
     #[test]
     fn test_operations() {
         let mut computer = Intcode::new([1002, 4, 3, 4, 33].to_vec());
-        computer.compute(empty(), sink());
-        assert_eq!(computer.program, [1002, 4, 3, 4, 99]);
+        computer.compute(empty(), sink()).unwrap();
+        assert_eq!(computer.memory.to_vec(), [1002, 4, 3, 4, 99]);
 
-        let input = b"8";
+        let mut input = VecDeque::from([8]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"1\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1]);
 
-        let input = b"9";
+        let mut input = VecDeque::from([9]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"0\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [0]);
 
-        let input = b"8";
+        let mut input = VecDeque::from([8]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"0\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [0]);
 
-        let input = b"7";
+        let mut input = VecDeque::from([7]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"1\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1]);
 
-        let input = b"8";
+        let mut input = VecDeque::from([8]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 3, 1108, -1, 8, 3, 4, 3, 99].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"1\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1]);
 
-        let input = b"9";
+        let mut input = VecDeque::from([9]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 3, 1108, -1, 8, 3, 4, 3, 99].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"0\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [0]);
 
-        let input = b"8";
+        let mut input = VecDeque::from([8]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 3, 1107, -1, 8, 3, 4, 3, 99].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"0\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [0]);
 
-        let input = b"7";
+        let mut input = VecDeque::from([7]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 3, 1107, -1, 8, 3, 4, 3, 99].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"1\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1]);
 
-        let input = b"2";
+        let mut input = VecDeque::from([2]);
         let mut output = Vec::new();
         let mut computer =
             Intcode::new([3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"1\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1]);
 
-        let input = b"0";
+        let mut input = VecDeque::from([0]);
         let mut output = Vec::new();
         let mut computer =
             Intcode::new([3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"0\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [0]);
 
-        let input = b"2";
+        let mut input = VecDeque::from([2]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"1\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1]);
 
-        let input = b"0";
+        let mut input = VecDeque::from([0]);
         let mut output = Vec::new();
         let mut computer = Intcode::new([3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1].to_vec());
-        computer.compute(&input[..], &mut output);
-        assert_eq!(output, b"0\n");
+        computer.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [0]);
 
         let computer = Intcode::new(
             [
@@ -332,23 +499,23 @@ mod tests {
             .to_vec(),
         );
 
-        let input = b"7";
+        let mut input = VecDeque::from([7]);
         let mut output = Vec::new();
         let mut comp = computer.clone();
-        comp.compute(&input[..], &mut output);
-        assert_eq!(output, b"999\n");
+        comp.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [999]);
 
-        let input = b"8";
+        let mut input = VecDeque::from([8]);
         let mut output = Vec::new();
         let mut comp = computer.clone();
-        comp.compute(&input[..], &mut output);
-        assert_eq!(output, b"1000\n");
+        comp.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1000]);
 
-        let input = b"9";
+        let mut input = VecDeque::from([9]);
         let mut output = Vec::new();
         let mut comp = computer.clone();
-        comp.compute(&input[..], &mut output);
-        assert_eq!(output, b"1001\n");
+        comp.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1001]);
     }
 
     #[test]
@@ -360,7 +527,7 @@ mod tests {
             ]
             .to_vec(),
         );
-        comp.compute(empty(), &mut output);
+        comp.compute(empty(), TextOutput(&mut output)).unwrap();
         assert_eq!(
             String::from_utf8(output).unwrap(),
             "109\n1\n204\n-1\n1001\n100\n1\n100\n1008\n100\n16\n101\n1006\n101\n0\n99\n"
@@ -368,22 +535,118 @@ mod tests {
 
         let mut output = Vec::new();
         let mut comp = Intcode::new([1102, 34915192, 34915192, 7, 4, 7, 99, 0].to_vec());
-        comp.compute(empty(), &mut output);
-        let result = String::from_utf8(output)
-            .unwrap()
-            .trim()
-            .parse::<u64>()
-            .unwrap();
-        assert!(result / 1_000_000_000_000_000 > 0);
+        comp.compute(empty(), &mut output).unwrap();
+        assert!(output[0] / 1_000_000_000_000_000 > 0);
 
         let mut output = Vec::new();
         let mut comp = Intcode::new([104, 1125899906842624, 99].to_vec());
-        comp.compute(empty(), &mut output);
-        let result = String::from_utf8(output)
-            .unwrap()
-            .trim()
-            .parse::<u64>()
-            .unwrap();
-        assert_eq!(result, 1125899906842624);
+        comp.compute(empty(), &mut output).unwrap();
+        assert_eq!(output, [1125899906842624]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut comp = Intcode::new([1, 0, 0, 0, 42].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownOpcode(42));
+        assert_eq!(error.instruction_ptr, 4);
+        assert_eq!(error.instruction, 42);
+
+        let mut comp = Intcode::new([30001, 0, 0, 0, 99].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownParameterMode(3));
+        assert_eq!(error.instruction, 30001);
+
+        let mut comp = Intcode::new([11101, 1, 1, 0, 99].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::ImmediateWrite);
+
+        let mut comp = Intcode::new([4, -1, 99].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidAddress(-1));
+        assert_eq!(error.instruction_ptr, 0);
+
+        let mut comp = Intcode::new([1106, 0, -7, 99].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidAddress(-7));
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut program = vec![0; 4096];
+        program[..4].copy_from_slice(&[1101, 2, 3, 4095]);
+        program[4] = 99;
+        let mut comp = Intcode::new(program);
+        comp.compute(empty(), sink()).unwrap();
+        assert_eq!(comp.memory[4095], 5);
+
+        let mut comp = Intcode::new([1101, 2, 3, 1_000_000_000, 99].to_vec());
+        comp.compute(empty(), sink()).unwrap();
+        assert_eq!(comp.memory[1_000_000_000], 5);
+
+        let mut comp = Intcode::with_memory_limit([1101, 2, 3, 100, 99].to_vec(), 100);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidAddress(100));
+
+        // Relative addresses that overflow are errors rather than panics.
+        let mut comp = Intcode::new([109, 1, 204, i64::MAX, 99].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Overflow);
+        assert_eq!(error.instruction_ptr, 2);
+        comp.set_arithmetic(Arithmetic::Wrapping);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidAddress(i64::MIN));
+    }
+
+    #[test]
+    fn test_input_errors() {
+        let program = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec();
+
+        let mut output = Vec::new();
+        let mut comp = Intcode::new(program.clone());
+        let status = comp.compute(TextInput(&b""[..]), &mut output).unwrap();
+        assert_eq!(status, ExitStatus::AwaitingInput);
+
+        let error = comp
+            .compute(TextInput(&b"8x\n"[..]), &mut output)
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::MalformedInput("8x".to_string()));
+        assert_eq!(error.instruction_ptr, 0);
+        assert_eq!(error.instruction, 3);
+
+        let status = comp.compute(TextInput(&b"8\n"[..]), &mut output).unwrap();
+        assert_eq!(status, ExitStatus::Terminated);
+        assert_eq!(output, [1]);
+    }
+
+    #[test]
+    fn test_stepping() {
+        let program = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec();
+
+        let mut comp = Intcode::new(program.clone());
+        let mut input = VecDeque::new();
+        assert_eq!(
+            comp.run_until_output(&mut input).unwrap(),
+            ExitStatus::AwaitingInput
+        );
+        input.push_back(8);
+        assert_eq!(
+            comp.run_until_output(&mut input).unwrap(),
+            ExitStatus::Output(1)
+        );
+        assert_eq!(
+            comp.run_until_output(&mut input).unwrap(),
+            ExitStatus::Terminated
+        );
+
+        let mut comp = Intcode::new(program);
+        let mut input = VecDeque::from([8]);
+        assert_eq!(comp.step(&mut input).unwrap(), None);
+        assert_eq!(comp.instruction_ptr, 2);
+        assert_eq!(comp.step(&mut input).unwrap(), None);
+        assert_eq!(comp.step(&mut input).unwrap(), Some(ExitStatus::Output(1)));
+        assert_eq!(comp.instruction_ptr, 8);
+        assert_eq!(comp.step(&mut input).unwrap(), Some(ExitStatus::Terminated));
+        assert_eq!(comp.step(&mut input).unwrap(), Some(ExitStatus::Terminated));
     }
 }
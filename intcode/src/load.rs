@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// A value that isn't an integer, and its byte offset in the source.
+    Malformed {
+        offset: usize,
+        token: String,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Malformed { offset, token } => {
+                write!(f, "malformed value {:?} at byte {}", token, offset)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl From<LoadError> for io::Error {
+    fn from(e: LoadError) -> Self {
+        match e {
+            LoadError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// Parses a program of comma-separated integers. Whitespace, including newlines, may appear
+/// around or between values, and a single trailing comma is allowed.
+pub fn parse_program(source: &str) -> Result<Vec<i64>, LoadError> {
+    let offset = |s: &str| s.as_ptr() as usize - source.as_ptr() as usize;
+    let mut fields = source.split(',').collect::<Vec<_>>();
+    if fields.len() > 1 && fields.last().is_some_and(|f| f.trim().is_empty()) {
+        fields.pop();
+    }
+    if fields.len() == 1 && fields[0].trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut program = Vec::new();
+    for field in fields {
+        let mut tokens = field.split_whitespace().peekable();
+        if tokens.peek().is_none() {
+            return Err(LoadError::Malformed {
+                offset: offset(field),
+                token: String::new(),
+            });
+        }
+        for token in tokens {
+            let value = token.parse().map_err(|_| LoadError::Malformed {
+                offset: offset(token),
+                token: token.to_string(),
+            })?;
+            program.push(value);
+        }
+    }
+    Ok(program)
+}
+
+pub fn load_program<P: AsRef<Path>>(path: P) -> Result<Vec<i64>, LoadError> {
+    parse_program(&fs::read_to_string(path)?)
+}
+
+pub fn load_program_stdin() -> Result<Vec<i64>, LoadError> {
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+    parse_program(&source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_program() {
+        assert_eq!(parse_program("1,0,0,3,99\n").unwrap(), [1, 0, 0, 3, 99]);
+        assert_eq!(parse_program(" 1, -2 ,\n3\r\n4,").unwrap(), [1, -2, 3, 4]);
+        assert_eq!(parse_program(" \n").unwrap(), []);
+
+        let malformed = |source| match parse_program(source) {
+            Err(LoadError::Malformed { offset, token }) => (offset, token),
+            result => panic!("expected an error, got {:?}", result),
+        };
+        assert_eq!(malformed("1,2,x3,4"), (4, String::from("x3")));
+        assert_eq!(
+            malformed("1,\n 99999999999999999999"),
+            (4, String::from("99999999999999999999"))
+        );
+        assert_eq!(malformed("1,,2"), (2, String::new()));
+
+        let error = io::Error::from(parse_program("1,+").err().unwrap());
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            load_program("does-not-exist.txt"),
+            Err(LoadError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
+
+use crate::{ErrorKind, Operation, Word};
+
+/// Addresses below this grow the dense backing vector on write; anything higher is stored sparsely.
+const DENSE_LIMIT: usize = 1 << 16;
+
+/// Intcode memory of `W` cells. Unwritten cells read as zero, and the program may address any
+/// cell below the optional limit.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Memory<W = i64> {
+    dense: Vec<W>,
+    sparse: HashMap<usize, W>,
+    /// One past the highest sparse address written, so `len` doesn't scan the sparse cells.
+    sparse_end: usize,
+    limit: Option<usize>,
+    decoded: Decoded,
+    writes: Writes,
+}
+
+/// Instructions already decoded from the dense cells, by address. Every write through `set`
+/// clears the entry for the written cell, so self-modifying programs are decoded afresh.
+#[derive(Clone, Default)]
+struct Decoded(Vec<Option<Operation>>);
+
+/// The cache only mirrors the cells, so it never makes two memories differ.
+impl PartialEq for Decoded {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// How many times `set` has been called, so the loop detector can tell when memory was edited
+/// without it seeing the write. Like the decode cache, it never makes two memories differ.
+#[derive(Clone, Copy, Default)]
+struct Writes(u64);
+
+impl PartialEq for Writes {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for Writes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} writes", self.0)
+    }
+}
+
+impl fmt::Debug for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} decoded", self.0.iter().flatten().count())
+    }
+}
+
+impl<W: Word> Memory<W> {
+    pub fn new(program: Vec<W>) -> Self {
+        Memory {
+            dense: program,
+            sparse: HashMap::new(),
+            sparse_end: 0,
+            limit: None,
+            decoded: Decoded::default(),
+            writes: Writes::default(),
+        }
+    }
+
+    /// Memory where addresses at or above `limit` are invalid.
+    pub fn with_limit(program: Vec<W>, limit: usize) -> Self {
+        Memory {
+            limit: Some(limit),
+            ..Self::new(program)
+        }
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Whether `address` may be read or written.
+    pub fn contains(&self, address: usize) -> bool {
+        self.limit.is_none_or(|limit| address < limit)
+    }
+
+    pub fn get(&self, address: usize) -> W {
+        match self.dense.get(address) {
+            Some(value) => value.clone(),
+            None => self
+                .sparse
+                .get(&address)
+                .cloned()
+                .unwrap_or_else(|| W::from(0)),
+        }
+    }
+
+    pub fn set(&mut self, address: usize, value: W) {
+        self.writes.0 = self.writes.0.wrapping_add(1);
+        if let Some(entry) = self.decoded.0.get_mut(address) {
+            *entry = None;
+        }
+        if address < self.dense.len() {
+            self.dense[address] = value;
+        } else if address < DENSE_LIMIT {
+            self.dense.resize(address + 1, W::from(0));
+            self.dense[address] = value;
+        } else {
+            self.sparse_end = self.sparse_end.max(address + 1);
+            self.sparse.insert(address, value);
+        }
+    }
+
+    /// The number of writes through `set` so far.
+    pub(crate) fn writes(&self) -> u64 {
+        self.writes.0
+    }
+
+    /// The instruction at `address`, decoded once and then served from a cache until the cell
+    /// is written.
+    pub(crate) fn decode(&mut self, address: usize) -> Result<Operation, ErrorKind> {
+        if address >= DENSE_LIMIT {
+            return Operation::parse(self.get(address).instruction());
+        }
+        if let Some(Some(operation)) = self.decoded.0.get(address) {
+            return Ok(*operation);
+        }
+        let operation = Operation::parse(self.get(address).instruction())?;
+        if address >= self.decoded.0.len() {
+            self.decoded.0.resize(address + 1, None);
+        }
+        self.decoded.0[address] = Some(operation);
+        Ok(operation)
+    }
+
+    /// One past the highest address that has been loaded or written.
+    pub fn len(&self) -> usize {
+        self.dense.len().max(self.sparse_end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every stored cell in address order: the dense prefix, then sparse cells.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, W)> + '_ {
+        let mut sparse = self
+            .sparse
+            .iter()
+            .map(|(a, v)| (*a, v.clone()))
+            .collect::<Vec<_>>();
+        sparse.sort_unstable_by_key(|(address, _)| *address);
+        self.dense.iter().cloned().enumerate().chain(sparse)
+    }
+
+    /// The dense prefix of memory: the program and any cells written below the sparse range.
+    /// Sparse cells are left out; use `cells` to see every stored cell.
+    pub fn to_vec(&self) -> Vec<W> {
+        self.dense.clone()
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i64;
+
+    fn index(&self, address: usize) -> &i64 {
+        match self.dense.get(address) {
+            Some(value) => value,
+            None => self.sparse.get(&address).unwrap_or(&0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParameterMode;
+
+    #[test]
+    fn test_growth() {
+        let mut memory = Memory::new([1, 2, 3].to_vec());
+        assert_eq!(memory.get(10), 0);
+        memory.set(10, 7);
+        assert_eq!(memory.get(10), 7);
+        assert_eq!(memory.len(), 11);
+
+        memory.set(1 << 40, 5);
+        assert_eq!(memory.get(1 << 40), 5);
+        assert_eq!(memory[1 << 40], 5);
+        assert_eq!(memory[(1 << 40) + 1], 0);
+        assert_eq!(memory.len(), (1 << 40) + 1);
+        assert_eq!(memory.to_vec().len(), 11);
+        assert_eq!(memory.cells().last(), Some((1 << 40, 5)));
+    }
+
+    #[test]
+    fn test_limit() {
+        let memory = Memory::with_limit([1, 2, 3].to_vec(), 16);
+        assert!(memory.contains(15));
+        assert!(!memory.contains(16));
+        assert!(Memory::<i64>::new(Vec::new()).contains(usize::MAX));
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut memory = Memory::new([1002, 4, 3, 4, 33].to_vec());
+        let multiply = Operation::Multiplication(
+            ParameterMode::Position,
+            ParameterMode::Immediate,
+            ParameterMode::Position,
+        );
+        assert_eq!(memory.decode(0), Ok(multiply));
+        assert_eq!(memory.decode(0), Ok(multiply));
+        assert_eq!(memory.decode(4), Err(ErrorKind::UnknownOpcode(33)));
+
+        memory.set(0, 99);
+        assert_eq!(memory.decode(0), Ok(Operation::Terminate));
+        assert_eq!(memory, Memory::new([99, 4, 3, 4, 33].to_vec()));
+    }
+}
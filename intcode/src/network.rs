@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+use crate::{ExitStatus, Intcode, IntcodeError};
+
+/// Where a machine's outputs go.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Route {
+    /// Kept for the caller, readable with `Network::output`.
+    Collect,
+    /// Appended to another machine's input.
+    Link(usize),
+    /// Grouped into packets of an address followed by `payload` values. The payload goes to
+    /// the input of the machine with that index, or to `Network::take_unrouted` if there is none.
+    Packets { payload: usize },
+}
+
+/// A packet addressed to a machine that isn't in the network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub address: i64,
+    pub payload: Vec<i64>,
+}
+
+/// Why `Network::run` returned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetworkStatus {
+    /// Every machine has stopped.
+    Halted,
+    /// No running machine has input to process, so nothing will change until the caller sends
+    /// some.
+    Idle,
+}
+
+/// A machine in the network that failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkError {
+    pub machine: usize,
+    pub error: IntcodeError,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "machine {}: {}", self.machine, self.error)
+    }
+}
+
+impl Error for NetworkError {}
+
+#[derive(Clone)]
+struct Node {
+    machine: Intcode,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+    route: Route,
+    packet: Vec<i64>,
+    /// The status the machine stopped with, once it can run no further.
+    stopped: Option<ExitStatus>,
+}
+
+/// Several machines whose outputs feed each other's inputs, run round-robin on one thread.
+#[derive(Clone, Default)]
+pub struct Network {
+    nodes: Vec<Node>,
+    idle_input: Option<i64>,
+    unrouted: Vec<Packet>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a machine that collects its outputs, returning its index.
+    pub fn add(&mut self, machine: Intcode) -> usize {
+        self.nodes.push(Node {
+            machine,
+            input: VecDeque::new(),
+            output: Vec::new(),
+            route: Route::Collect,
+            packet: Vec::new(),
+            stopped: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Sets where a machine's outputs go.
+    ///
+    /// # Panics
+    ///
+    /// If `route` links to a machine that hasn't been added.
+    pub fn route(&mut self, machine: usize, route: Route) {
+        if let Route::Link(to) = route {
+            assert!(
+                to < self.nodes.len(),
+                "cannot link to machine {}, the network has {}",
+                to,
+                self.nodes.len()
+            );
+        }
+        self.nodes[machine].route = route;
+    }
+
+    /// A value given to any machine that asks for input when it has none, as in a network
+    /// where reading -1 means no packet has arrived. Without one, such machines wait.
+    pub fn set_idle_input(&mut self, value: Option<i64>) {
+        self.idle_input = value;
+    }
+
+    pub fn send(&mut self, machine: usize, value: i64) {
+        self.nodes[machine].input.push_back(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn machine(&self, machine: usize) -> &Intcode {
+        &self.nodes[machine].machine
+    }
+
+    /// Values sent to the machine that it hasn't read yet.
+    pub fn input(&self, machine: usize) -> &VecDeque<i64> {
+        &self.nodes[machine].input
+    }
+
+    /// Values output by a machine routed with `Route::Collect`.
+    pub fn output(&self, machine: usize) -> &[i64] {
+        &self.nodes[machine].output
+    }
+
+    /// The status a machine stopped with, or `None` if it can still run.
+    pub fn stopped(&self, machine: usize) -> Option<ExitStatus> {
+        self.nodes[machine].stopped
+    }
+
+    pub fn take_unrouted(&mut self) -> Vec<Packet> {
+        std::mem::take(&mut self.unrouted)
+    }
+
+    /// Gives each running machine a turn in order, until every machine has stopped or none
+    /// has anything to do. A turn lasts until the machine outputs a value, needs input it
+    /// doesn't have, or stops.
+    pub fn run(&mut self) -> Result<NetworkStatus, NetworkError> {
+        loop {
+            let mut busy = false;
+            for i in 0..self.nodes.len() {
+                if self.nodes[i].stopped.is_none() {
+                    busy |= self.turn(i)?;
+                }
+            }
+            if self.nodes.iter().all(|node| node.stopped.is_some()) {
+                return Ok(NetworkStatus::Halted);
+            }
+            if !busy {
+                return Ok(NetworkStatus::Idle);
+            }
+        }
+    }
+
+    /// Runs one machine's turn, returning whether it did anything besides wait for input.
+    fn turn(&mut self, i: usize) -> Result<bool, NetworkError> {
+        let idle_input = self.idle_input;
+        let node = &mut self.nodes[i];
+        let mut busy = !node.input.is_empty();
+        let run = |node: &mut Node| {
+            node.machine
+                .run_until_output(&mut node.input)
+                .map_err(|error| NetworkError { machine: i, error })
+        };
+        let mut status = run(node)?;
+        if let (ExitStatus::AwaitingInput, Some(value)) = (status, idle_input) {
+            node.input.push_back(value);
+            status = run(node)?;
+        }
+        match status {
+            ExitStatus::AwaitingInput => {}
+            ExitStatus::Output(value) => {
+                self.deliver(i, value);
+                busy = true;
+            }
+            status => {
+                node.stopped = Some(status);
+                busy = true;
+            }
+        }
+        Ok(busy)
+    }
+
+    fn deliver(&mut self, from: usize, value: i64) {
+        match self.nodes[from].route {
+            Route::Collect => self.nodes[from].output.push(value),
+            Route::Link(to) => self.nodes[to].input.push_back(value),
+            Route::Packets { payload } => {
+                let packet = &mut self.nodes[from].packet;
+                packet.push(value);
+                if packet.len() <= payload {
+                    return;
+                }
+                let payload = packet.split_off(1);
+                let address = packet.pop().unwrap();
+                match self.nodes.get_mut(address as usize) {
+                    Some(node) if address >= 0 => node.input.extend(payload),
+                    _ => self.unrouted.push(Packet { address, payload }),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links() {
+        // Day 7's feedback loop: each amplifier reads a phase, then adjusts signals until done.
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut network = Network::new();
+        for phase in [9, 8, 7, 6, 5].iter() {
+            let i = network.add(Intcode::new(program.to_vec()));
+            network.send(i, *phase);
+        }
+        for i in 0..5 {
+            network.route(i, Route::Link((i + 1) % 5));
+        }
+        network.send(0, 0);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Halted);
+        assert_eq!(network.input(0), &[139629729]);
+        assert_eq!(network.stopped(4), Some(ExitStatus::Terminated));
+    }
+
+    #[test]
+    fn test_packets() {
+        // Reads an address and a value, then sends the value plus one to that address, forever.
+        let program = [
+            3, 100, 3, 101, 1001, 101, 1, 101, 4, 100, 4, 101, 1105, 1, 0,
+        ];
+        let mut network = Network::new();
+        for i in 0..2 {
+            network.add(Intcode::new(program.to_vec()));
+            network.route(i, Route::Packets { payload: 1 });
+        }
+        network.send(0, 1);
+        network.send(0, 10);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Idle);
+        assert!(network.take_unrouted().is_empty());
+
+        network.send(1, 5);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Idle);
+        assert_eq!(
+            network.take_unrouted(),
+            [Packet {
+                address: 11,
+                payload: [6].to_vec()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_idle_input() {
+        // Outputs each input that isn't negative.
+        let program = [3, 20, 1007, 20, 0, 21, 1005, 21, 0, 4, 20, 1105, 1, 0];
+        let mut network = Network::new();
+        network.add(Intcode::new(program.to_vec()));
+        network.set_idle_input(Some(-1));
+        network.send(0, 3);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Idle);
+        assert_eq!(network.output(0), [3]);
+        assert!(network.input(0).is_empty());
+        assert_eq!(network.stopped(0), None);
+    }
+
+    #[test]
+    fn test_error() {
+        let mut network = Network::new();
+        network.add(Intcode::new([99].to_vec()));
+        network.add(Intcode::new([42].to_vec()));
+        let error = network.run().unwrap_err();
+        assert_eq!(error.machine, 1);
+        assert_eq!(error.to_string(), format!("machine 1: {}", error.error));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_missing_link() {
+        let mut network = Network::new();
+        network.add(Intcode::new([99].to_vec()));
+        network.route(0, Route::Link(5));
+    }
+}
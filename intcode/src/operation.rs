@@ -0,0 +1,200 @@
+use crate::ErrorKind;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl ParameterMode {
+    /// The mode of the parameter at `position` (counting from zero) in `instruction`.
+    pub fn parse(instruction: i64, position: u32) -> Result<Self, ErrorKind> {
+        match instruction / (10i64.pow(position + 2)) % 10 {
+            0 => Ok(Self::Position),
+            1 => Ok(Self::Immediate),
+            2 => Ok(Self::Relative),
+            p => Err(ErrorKind::UnknownParameterMode(p)),
+        }
+    }
+
+    pub fn digit(&self) -> i64 {
+        match self {
+            Self::Position => 0,
+            Self::Immediate => 1,
+            Self::Relative => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Addition(ParameterMode, ParameterMode, ParameterMode),
+    Multiplication(ParameterMode, ParameterMode, ParameterMode),
+    Input(ParameterMode),
+    Output(ParameterMode),
+    JumpTrue(ParameterMode, ParameterMode),
+    JumpFalse(ParameterMode, ParameterMode),
+    Less(ParameterMode, ParameterMode, ParameterMode),
+    Equal(ParameterMode, ParameterMode, ParameterMode),
+    ChangeRelativeBase(ParameterMode),
+    Terminate,
+}
+
+impl Operation {
+    pub fn parse(instruction: i64) -> Result<Self, ErrorKind> {
+        let op_code = instruction % 100;
+        Ok(match op_code {
+            1 => Self::Addition(
+                ParameterMode::parse(instruction, 0)?,
+                ParameterMode::parse(instruction, 1)?,
+                ParameterMode::parse(instruction, 2)?,
+            ),
+            2 => Self::Multiplication(
+                ParameterMode::parse(instruction, 0)?,
+                ParameterMode::parse(instruction, 1)?,
+                ParameterMode::parse(instruction, 2)?,
+            ),
+            3 => Self::Input(ParameterMode::parse(instruction, 0)?),
+            4 => Self::Output(ParameterMode::parse(instruction, 0)?),
+            5 => Self::JumpTrue(
+                ParameterMode::parse(instruction, 0)?,
+                ParameterMode::parse(instruction, 1)?,
+            ),
+            6 => Self::JumpFalse(
+                ParameterMode::parse(instruction, 0)?,
+                ParameterMode::parse(instruction, 1)?,
+            ),
+            7 => Self::Less(
+                ParameterMode::parse(instruction, 0)?,
+                ParameterMode::parse(instruction, 1)?,
+                ParameterMode::parse(instruction, 2)?,
+            ),
+            8 => Self::Equal(
+                ParameterMode::parse(instruction, 0)?,
+                ParameterMode::parse(instruction, 1)?,
+                ParameterMode::parse(instruction, 2)?,
+            ),
+            9 => Self::ChangeRelativeBase(ParameterMode::parse(instruction, 0)?),
+            99 => Self::Terminate,
+            c => return Err(ErrorKind::UnknownOpcode(c)),
+        })
+    }
+
+    pub fn opcode(&self) -> i64 {
+        match self {
+            Self::Addition(_, _, _) => 1,
+            Self::Multiplication(_, _, _) => 2,
+            Self::Input(_) => 3,
+            Self::Output(_) => 4,
+            Self::JumpTrue(_, _) => 5,
+            Self::JumpFalse(_, _) => 6,
+            Self::Less(_, _, _) => 7,
+            Self::Equal(_, _, _) => 8,
+            Self::ChangeRelativeBase(_) => 9,
+            Self::Terminate => 99,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Addition(_, _, _) => "ADD",
+            Self::Multiplication(_, _, _) => "MUL",
+            Self::Input(_) => "IN",
+            Self::Output(_) => "OUT",
+            Self::JumpTrue(_, _) => "JT",
+            Self::JumpFalse(_, _) => "JF",
+            Self::Less(_, _, _) => "LT",
+            Self::Equal(_, _, _) => "EQ",
+            Self::ChangeRelativeBase(_) => "ARB",
+            Self::Terminate => "HLT",
+        }
+    }
+
+    pub fn parameter_modes(&self) -> Vec<ParameterMode> {
+        match *self {
+            Self::Addition(p1, p2, p3)
+            | Self::Multiplication(p1, p2, p3)
+            | Self::Less(p1, p2, p3)
+            | Self::Equal(p1, p2, p3) => vec![p1, p2, p3],
+            Self::JumpTrue(p1, p2) | Self::JumpFalse(p1, p2) => vec![p1, p2],
+            Self::Input(p1) | Self::Output(p1) | Self::ChangeRelativeBase(p1) => vec![p1],
+            Self::Terminate => Vec::new(),
+        }
+    }
+
+    /// The operation named by `mnemonic` (in any case), if `modes` has the right length.
+    pub fn from_mnemonic(mnemonic: &str, modes: &[ParameterMode]) -> Option<Self> {
+        Some(match (mnemonic.to_uppercase().as_str(), modes) {
+            ("ADD", &[p1, p2, p3]) => Self::Addition(p1, p2, p3),
+            ("MUL", &[p1, p2, p3]) => Self::Multiplication(p1, p2, p3),
+            ("IN", &[p1]) => Self::Input(p1),
+            ("OUT", &[p1]) => Self::Output(p1),
+            ("JT", &[p1, p2]) => Self::JumpTrue(p1, p2),
+            ("JF", &[p1, p2]) => Self::JumpFalse(p1, p2),
+            ("LT", &[p1, p2, p3]) => Self::Less(p1, p2, p3),
+            ("EQ", &[p1, p2, p3]) => Self::Equal(p1, p2, p3),
+            ("ARB", &[p1]) => Self::ChangeRelativeBase(p1),
+            ("HLT", &[]) => Self::Terminate,
+            _ => return None,
+        })
+    }
+
+    /// The index of the parameter this operation writes to, if any.
+    pub fn write_parameter(&self) -> Option<usize> {
+        match self {
+            Self::Addition(_, _, _)
+            | Self::Multiplication(_, _, _)
+            | Self::Less(_, _, _)
+            | Self::Equal(_, _, _) => Some(2),
+            Self::Input(_) => Some(0),
+            _ => None,
+        }
+    }
+
+    /// The instruction word for this operation.
+    pub fn encode(&self) -> i64 {
+        self.parameter_modes()
+            .iter()
+            .enumerate()
+            .fold(self.opcode(), |word, (position, mode)| {
+                word + mode.digit() * 10i64.pow(position as u32 + 2)
+            })
+    }
+
+    pub(crate) fn advance(&self) -> usize {
+        match self {
+            Self::Addition(_, _, _) => 4,
+            Self::Multiplication(_, _, _) => 4,
+            Self::Input(_) => 2,
+            Self::Output(_) => 2,
+            Self::JumpTrue(_, _) => 3,
+            Self::JumpFalse(_, _) => 3,
+            Self::Less(_, _, _) => 4,
+            Self::Equal(_, _, _) => 4,
+            Self::ChangeRelativeBase(_) => 2,
+            Self::Terminate => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        for &word in [
+            1, 1002, 21101, 3, 203, 104, 1105, 2206, 1107, 20008, 109, 99,
+        ]
+        .iter()
+        {
+            assert_eq!(Operation::parse(word).unwrap().encode(), word);
+        }
+        assert_eq!(Operation::parse(100099).unwrap().encode(), 99);
+        assert_eq!(
+            Operation::parse(1205).unwrap().parameter_modes(),
+            [ParameterMode::Relative, ParameterMode::Immediate]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{Arithmetic, ErrorKind, ExitStatus, IntcodeInput, Operation, ParameterMode};
+
+/// A value a machine's memory can hold: `i64`, or `BigInt` with the `bigint` feature.
+pub trait Word: Clone + PartialEq + PartialOrd + From<i64> {
+    /// The sum, overflowing as `arithmetic` says if the type is bounded.
+    fn add(self, other: Self, arithmetic: Arithmetic) -> Result<Self, ErrorKind>;
+
+    /// The product, overflowing as `arithmetic` says if the type is bounded.
+    fn mul(self, other: Self, arithmetic: Arithmetic) -> Result<Self, ErrorKind>;
+
+    /// The value, if it fits in an `i64`.
+    fn as_i64(&self) -> Option<i64>;
+
+    /// The value clamped to the range of an `i64`, for error reports.
+    fn saturating_i64(&self) -> i64;
+
+    /// An `i64` with the same opcode and parameter modes, for decoding the word as an
+    /// instruction.
+    fn instruction(&self) -> i64;
+}
+
+impl Word for i64 {
+    fn add(self, other: Self, arithmetic: Arithmetic) -> Result<Self, ErrorKind> {
+        arithmetic.add(self, other)
+    }
+
+    fn mul(self, other: Self, arithmetic: Arithmetic) -> Result<Self, ErrorKind> {
+        arithmetic.mul(self, other)
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn saturating_i64(&self) -> i64 {
+        *self
+    }
+
+    fn instruction(&self) -> i64 {
+        *self
+    }
+}
+
+/// The parts of a machine an instruction works on. `execute` gives every instruction its
+/// meaning in terms of these, for machines of any word type.
+pub(crate) trait Processor {
+    type Word: Word;
+
+    fn arithmetic(&self) -> Arithmetic;
+
+    /// The value of the parameter at `offset` from the instruction pointer.
+    fn parameter(&mut self, offset: usize, mode: ParameterMode) -> Result<Self::Word, ErrorKind>;
+
+    /// Writes to the address the parameter at `offset` refers to.
+    fn write_parameter(
+        &mut self,
+        offset: usize,
+        value: Self::Word,
+        mode: ParameterMode,
+    ) -> Result<(), ErrorKind>;
+
+    /// Moves the instruction pointer to `target`, which must be a valid address.
+    fn jump(&mut self, target: Self::Word) -> Result<(), ErrorKind>;
+
+    /// Moves the instruction pointer past the current instruction.
+    fn advance(&mut self, by: usize);
+
+    fn relative_base(&self) -> Self::Word;
+
+    fn set_relative_base(&mut self, relative_base: Self::Word);
+}
+
+/// Runs `operation`, the instruction at the instruction pointer.
+pub(crate) fn execute<P, I>(
+    machine: &mut P,
+    operation: Operation,
+    input: &mut I,
+) -> Result<Option<ExitStatus<P::Word>>, ErrorKind>
+where
+    P: Processor,
+    I: IntcodeInput<P::Word>,
+{
+    let mut advance = operation.advance();
+    let truth = |condition: bool| P::Word::from(i64::from(condition));
+    match operation {
+        Operation::Addition(p1, p2, p3) => {
+            let value = machine
+                .parameter(1, p1)?
+                .add(machine.parameter(2, p2)?, machine.arithmetic())?;
+            machine.write_parameter(3, value, p3)?;
+        }
+        Operation::Multiplication(p1, p2, p3) => {
+            let value = machine
+                .parameter(1, p1)?
+                .mul(machine.parameter(2, p2)?, machine.arithmetic())?;
+            machine.write_parameter(3, value, p3)?;
+        }
+        Operation::Input(p1) => match input.read()? {
+            Some(value) => machine.write_parameter(1, value, p1)?,
+            None => return Ok(Some(ExitStatus::AwaitingInput)),
+        },
+        Operation::Output(p1) => {
+            let value = machine.parameter(1, p1)?;
+            machine.advance(advance);
+            return Ok(Some(ExitStatus::Output(value)));
+        }
+        Operation::JumpTrue(p1, p2) => {
+            if machine.parameter(1, p1)? != truth(false) {
+                advance = 0;
+                let target = machine.parameter(2, p2)?;
+                machine.jump(target)?;
+            }
+        }
+        Operation::JumpFalse(p1, p2) => {
+            if machine.parameter(1, p1)? == truth(false) {
+                advance = 0;
+                let target = machine.parameter(2, p2)?;
+                machine.jump(target)?;
+            }
+        }
+        Operation::Less(p1, p2, p3) => {
+            let value = truth(machine.parameter(1, p1)? < machine.parameter(2, p2)?);
+            machine.write_parameter(3, value, p3)?;
+        }
+        Operation::Equal(p1, p2, p3) => {
+            let value = truth(machine.parameter(1, p1)? == machine.parameter(2, p2)?);
+            machine.write_parameter(3, value, p3)?;
+        }
+        Operation::ChangeRelativeBase(p1) => {
+            let offset = machine.parameter(1, p1)?;
+            let relative_base = machine.relative_base().add(offset, machine.arithmetic())?;
+            machine.set_relative_base(relative_base);
+        }
+        Operation::Terminate => return Ok(Some(ExitStatus::Terminated)),
+    }
+    machine.advance(advance);
+    Ok(None)
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Intcode, Operation};
+
+/// Rows shown per table by `Profile`'s `Display` implementation.
+const REPORT_ROWS: usize = 20;
+
+/// Execution counts gathered while profiling was enabled.
+///
+/// Reads and writes count operand accesses only: the instruction words and parameters fetched
+/// to decode each instruction are not included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub instructions: u64,
+    pub opcodes: HashMap<&'static str, u64>,
+    pub addresses: HashMap<usize, u64>,
+    pub reads: HashMap<usize, u64>,
+    pub writes: HashMap<usize, u64>,
+}
+
+impl Profile {
+    fn record(
+        &mut self,
+        instruction_ptr: usize,
+        operation: Operation,
+        operands: &[(i64, Option<usize>)],
+    ) {
+        self.instructions += 1;
+        *self.opcodes.entry(operation.mnemonic()).or_insert(0) += 1;
+        *self.addresses.entry(instruction_ptr).or_insert(0) += 1;
+        for (i, (_, address)) in operands.iter().enumerate() {
+            if let Some(address) = address {
+                let counts = match Some(i) == operation.write_parameter() {
+                    true => &mut self.writes,
+                    false => &mut self.reads,
+                };
+                *counts.entry(*address).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Instruction addresses by execution count, hottest first.
+    pub fn hot_spots(&self) -> Vec<(usize, u64)> {
+        sorted(&self.addresses)
+    }
+}
+
+/// Entries by count, highest first, breaking ties by key.
+fn sorted<K: Copy + Ord>(counts: &HashMap<K, u64>) -> Vec<(K, u64)> {
+    let mut counts = counts.iter().map(|(k, c)| (*k, *c)).collect::<Vec<_>>();
+    counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let share = |count: u64| 100.0 * count as f64 / self.instructions.max(1) as f64;
+        writeln!(f, "{} instructions", self.instructions)?;
+
+        writeln!(f, "\n{:>8} {:>12} {:>7}", "opcode", "count", "share")?;
+        for (mnemonic, count) in sorted(&self.opcodes) {
+            writeln!(f, "{:>8} {:>12} {:>6.2}%", mnemonic, count, share(count))?;
+        }
+
+        writeln!(f, "\n{:>8} {:>12} {:>7}", "address", "count", "share")?;
+        for (address, count) in self.hot_spots().into_iter().take(REPORT_ROWS) {
+            writeln!(f, "{:>8} {:>12} {:>6.2}%", address, count, share(count))?;
+        }
+
+        for (title, counts) in [("read", &self.reads), ("written", &self.writes)].iter() {
+            writeln!(f, "\n{:>8} {:>12}", title, "count")?;
+            for (address, count) in sorted(counts).into_iter().take(REPORT_ROWS) {
+                writeln!(f, "{:>8} {:>12}", address, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Intcode {
+    /// Starts counting executed instructions and memory accesses, discarding any earlier profile.
+    pub fn enable_profile(&mut self) {
+        self.profile = Some(Profile::default());
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Stops profiling and returns the counts.
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    pub(crate) fn record_profile(
+        &mut self,
+        instruction_ptr: usize,
+        operation: Operation,
+        operands: &[(i64, Option<usize>)],
+    ) {
+        if let Some(profile) = self.profile.as_mut() {
+            profile.record(instruction_ptr, operation, operands);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{empty, sink};
+
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        // Counts 10 down to 0 in cell 9.
+        let mut comp = Intcode::new([1001, 9, -1, 9, 1005, 9, 0, 99, 0, 10].to_vec());
+        comp.enable_profile();
+        comp.compute(empty(), sink()).unwrap();
+        let profile = comp.take_profile().unwrap();
+
+        assert_eq!(profile.instructions, 21);
+        assert_eq!(profile.opcodes["ADD"], 10);
+        assert_eq!(profile.opcodes["JT"], 10);
+        assert_eq!(profile.opcodes["HLT"], 1);
+        assert_eq!(profile.hot_spots(), [(0, 10), (4, 10), (7, 1)]);
+        assert_eq!(profile.reads[&9], 20);
+        assert_eq!(profile.writes[&9], 10);
+        assert!(profile.to_string().starts_with("21 instructions\n"));
+        assert!(comp.profile().is_none());
+    }
+}
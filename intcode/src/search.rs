@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::{ExitStatus, Intcode, IterInput};
+
+/// One variant of a program to try: cells to overwrite before it runs, and its input.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Candidate {
+    pub patches: Vec<(usize, i64)>,
+    pub input: Vec<i64>,
+}
+
+impl Candidate {
+    /// A copy of `base` with the patches applied.
+    pub fn apply(&self, base: &Intcode) -> Intcode {
+        let mut machine = base.clone();
+        for (address, value) in &self.patches {
+            machine.memory.set(*address, *value);
+        }
+        machine
+    }
+}
+
+/// Scores every candidate across all cores and returns the best with its score. Candidates
+/// scored `None` are skipped, and ties go to the earliest candidate.
+pub fn search_by<T, S, I, F>(candidates: I, score: F) -> Option<(T, S)>
+where
+    I: IntoIterator<Item = T>,
+    T: Sync,
+    S: Ord + Send,
+    F: Fn(&T) -> Option<S> + Sync,
+{
+    let candidates = candidates.into_iter().collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    let best = thread::scope(|scope| {
+        let workers = (0..threads.min(candidates.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut best: Option<(S, usize)> = None;
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let candidate = match candidates.get(i) {
+                            Some(candidate) => candidate,
+                            None => return best,
+                        };
+                        if let Some(s) = score(candidate) {
+                            if best.as_ref().is_none_or(|(b, _)| s > *b) {
+                                best = Some((s, i));
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().expect("Search thread panicked."))
+            .max_by(|(a, i), (b, j)| a.cmp(b).then(j.cmp(i)))
+    })?;
+
+    let (score, index) = best;
+    Some((candidates.into_iter().nth(index)?, score))
+}
+
+/// Runs each candidate variant of `base` across all cores and returns the best by `score`,
+/// which is given the halted machine and its output. Candidates that fail or stop short of
+/// `ExitStatus::Terminated`, such as by running out of input or of a budget set on `base`,
+/// are skipped.
+pub fn search<I, S, F>(base: &Intcode, candidates: I, score: F) -> Option<(Candidate, S)>
+where
+    I: IntoIterator<Item = Candidate>,
+    S: Ord + Send,
+    F: Fn(&Intcode, &[i64]) -> Option<S> + Sync,
+{
+    search_by(candidates, |candidate| {
+        let mut machine = candidate.apply(base);
+        let mut output = Vec::new();
+        match machine.compute(IterInput(candidate.input.iter().copied()), &mut output) {
+            Ok(ExitStatus::Terminated) => score(&machine, &output),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        // [0] = a * b, with the immediate operands a and b patched in.
+        let base = Intcode::new([1102, 0, 0, 0, 99].to_vec());
+        let candidates = (0..10).flat_map(|a| {
+            (0..10).map(move |b| Candidate {
+                patches: [(1, a), (2, b)].to_vec(),
+                input: Vec::new(),
+            })
+        });
+        let (best, score) = search(&base, candidates.clone(), |machine, _| {
+            Some(machine.memory[0])
+        })
+        .unwrap();
+        assert_eq!(score, 9 * 9);
+        assert_eq!(best.patches, [(1, 9), (2, 9)]);
+
+        // Several candidates score the same; the first wins.
+        let (best, _) = search(&base, candidates, |machine, _| {
+            match machine.memory[0] == 0 {
+                true => Some(()),
+                false => None,
+            }
+        })
+        .unwrap();
+        assert_eq!(best.patches, [(1, 0), (2, 0)]);
+
+        // Outputs its input plus one.
+        let base = Intcode::new([3, 9, 101, 1, 9, 9, 4, 9, 99, 0].to_vec());
+        let candidates = [5, -2, 7].iter().map(|&i| Candidate {
+            patches: Vec::new(),
+            input: [i].to_vec(),
+        });
+        let (best, score) =
+            search(&base, candidates, |_, output| Some(Reverse(output[0]))).unwrap();
+        assert_eq!((best.input, score), ([-2].to_vec(), Reverse(-1)));
+
+        // Reads a value and spins forever on zero. Only the candidate that halts counts; the
+        // one left awaiting input and the one stopped by the budget are skipped.
+        let mut base = Intcode::new([3, 7, 1006, 7, 2, 99, 0, 0].to_vec());
+        base.set_budget(Some(10));
+        let inputs = [Vec::new(), [0].to_vec(), [1].to_vec()];
+        let candidates = inputs.iter().map(|input| Candidate {
+            patches: Vec::new(),
+            input: input.clone(),
+        });
+        let (best, ()) = search(&base, candidates, |_, _| Some(())).unwrap();
+        assert_eq!(best.input, [1]);
+
+        assert_eq!(search_by(Vec::<i64>::new(), |&x| Some(x)), None);
+        assert_eq!(search_by([3, -1, 8], |&x| Some(-x)), Some((-1, 1)));
+    }
+}
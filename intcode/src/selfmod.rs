@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{ErrorKind, Intcode};
+
+/// A write to a cell that is also run as part of an instruction, either before or after the
+/// write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfModification {
+    /// The address of the instruction that wrote the cell.
+    pub writer: usize,
+    pub address: usize,
+    pub value: i64,
+}
+
+impl fmt::Display for SelfModification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}: [{}] <- {}",
+            self.writer, self.address, self.value
+        )
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct SelfModDetector {
+    strict: bool,
+    /// Cells fetched as an instruction word or parameter.
+    executed: HashSet<usize>,
+    /// Cells written but not yet executed, with the last write to each.
+    written: HashMap<usize, SelfModification>,
+    found: Vec<SelfModification>,
+}
+
+impl SelfModDetector {
+    fn report(&mut self, write: SelfModification) -> Result<(), ErrorKind> {
+        self.found.push(write);
+        match self.strict {
+            true => Err(ErrorKind::SelfModifyingCode {
+                address: write.address,
+                writer: write.writer,
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Intcode {
+    /// Starts recording writes to cells that run as instructions, discarding any earlier
+    /// report. In strict mode such a write is an `ErrorKind::SelfModifyingCode` error instead.
+    ///
+    /// A write is caught when it happens if the cell has already run, or otherwise when the
+    /// cell first runs afterwards.
+    pub fn detect_self_modification(&mut self, strict: bool) {
+        self.self_mod = Some(SelfModDetector {
+            strict,
+            ..SelfModDetector::default()
+        });
+    }
+
+    pub fn self_modifications(&self) -> Option<&[SelfModification]> {
+        self.self_mod.as_ref().map(|detector| &detector.found[..])
+    }
+
+    /// Stops detection and returns what was found.
+    pub fn take_self_modifications(&mut self) -> Option<Vec<SelfModification>> {
+        self.self_mod.take().map(|detector| detector.found)
+    }
+
+    /// Marks the `size` cells of the instruction about to run as executed, reporting any that
+    /// were written earlier.
+    pub(crate) fn check_execute(&mut self, size: usize) -> Result<(), ErrorKind> {
+        let detector = match self.self_mod.as_mut() {
+            Some(detector) => detector,
+            None => return Ok(()),
+        };
+        let cells = self.instruction_ptr..self.instruction_ptr + size;
+        for address in cells {
+            detector.executed.insert(address);
+            if let Some(write) = detector.written.remove(&address) {
+                detector.report(write)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Notes a write by the instruction about to complete, reporting it if the cell has run.
+    pub(crate) fn check_write(&mut self, address: usize, value: i64) -> Result<(), ErrorKind> {
+        let write = SelfModification {
+            writer: self.instruction_ptr,
+            address,
+            value,
+        };
+        match self.self_mod.as_mut() {
+            Some(detector) if detector.executed.contains(&address) => detector.report(write),
+            Some(detector) => {
+                detector.written.insert(address, write);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{empty, sink};
+
+    use super::*;
+
+    #[test]
+    fn test_self_modification() {
+        // Rewrites its own last instruction from an addition into a halt, then runs it.
+        let program = [1101, 49, 50, 4, 1, 0, 0, 0].to_vec();
+        let mut comp = Intcode::new(program.clone());
+        comp.detect_self_modification(false);
+        comp.compute(empty(), sink()).unwrap();
+        let write = SelfModification {
+            writer: 0,
+            address: 4,
+            value: 99,
+        };
+        assert_eq!(comp.self_modifications(), Some(&[write][..]));
+        assert_eq!(write.to_string(), "0000: [4] <- 99");
+
+        let mut comp = Intcode::new(program);
+        comp.detect_self_modification(true);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(
+            error.kind,
+            ErrorKind::SelfModifyingCode {
+                address: 4,
+                writer: 0
+            }
+        );
+        assert_eq!(error.instruction_ptr, 4);
+
+        // Overwrites its own first instruction after running it.
+        let mut comp = Intcode::new([1101, 1, 1, 0, 99].to_vec());
+        comp.detect_self_modification(true);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.instruction_ptr, 0);
+        assert_eq!(comp.take_self_modifications().unwrap().len(), 1);
+
+        // Data writes aren't reported.
+        let mut comp = Intcode::new([1101, 1, 1, 5, 99, 0].to_vec());
+        comp.detect_self_modification(true);
+        comp.compute(empty(), sink()).unwrap();
+        assert_eq!(comp.self_modifications(), Some(&[][..]));
+    }
+}
@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::{Arithmetic, Intcode, Memory};
+
+const HEADER: &str = "intcode-snapshot 1";
+
+/// The full state of a paused machine, along with the input it has yet to read and the output
+/// its caller has yet to consume.
+///
+/// Snapshots are plain text, so they can be attached to a bug report. Tracing and other
+/// instrumentation is not saved.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub machine: Intcode,
+    pub input: Vec<i64>,
+    pub output: Vec<i64>,
+}
+
+impl Snapshot {
+    /// Input given back to the machine by `step_back` is saved ahead of `input`.
+    pub fn new(machine: &Intcode, input: &[i64], output: &[i64]) -> Self {
+        let mut machine = machine.clone();
+        let unread = std::mem::take(&mut machine.unread);
+        Snapshot {
+            machine,
+            input: unread.into_iter().chain(input.iter().copied()).collect(),
+            output: output.to_vec(),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let memory = &self.machine.memory;
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "ip {}", self.machine.instruction_ptr)?;
+        writeln!(writer, "rb {}", self.machine.relative_base)?;
+        writeln!(writer, "arithmetic {}", self.machine.arithmetic.name())?;
+        if let Some(limit) = memory.limit() {
+            writeln!(writer, "limit {}", limit)?;
+        }
+        writeln!(writer, "input {}", join(&self.input))?;
+        writeln!(writer, "output {}", join(&self.output))?;
+
+        let mut cells = memory.cells().peekable();
+        let mut dense = Vec::new();
+        while let Some((_, value)) = cells.next_if(|(address, _)| *address == dense.len()) {
+            dense.push(value);
+        }
+        writeln!(writer, "memory {}", join(&dense))?;
+        for (address, value) in cells {
+            writeln!(writer, "sparse {} {}", address, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<Snapshot> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("bad snapshot line: {:?}", line),
+            )
+        };
+
+        let mut lines = reader.lines();
+        match lines.next() {
+            Some(Ok(header)) if header == HEADER => {}
+            Some(Err(e)) => return Err(e),
+            _ => return Err(invalid("missing header")),
+        }
+
+        let mut instruction_ptr = 0;
+        let mut relative_base = 0;
+        let mut arithmetic = Arithmetic::default();
+        let mut limit = None;
+        let mut input = Vec::new();
+        let mut output = Vec::new();
+        let mut dense = Vec::new();
+        let mut sparse = Vec::new();
+        for line in lines {
+            let line = line?;
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], line[i + 1..].trim()),
+                None => (line.trim(), ""),
+            };
+            match key {
+                "ip" => instruction_ptr = value.parse().map_err(|_| invalid(&line))?,
+                "rb" => relative_base = value.parse().map_err(|_| invalid(&line))?,
+                "arithmetic" => {
+                    arithmetic = Arithmetic::from_name(value).ok_or_else(|| invalid(&line))?
+                }
+                "limit" => limit = Some(value.parse().map_err(|_| invalid(&line))?),
+                "input" => input = split(value).ok_or_else(|| invalid(&line))?,
+                "output" => output = split(value).ok_or_else(|| invalid(&line))?,
+                "memory" => dense = split(value).ok_or_else(|| invalid(&line))?,
+                "sparse" => {
+                    let cell = split(&value.replace(' ', ","))
+                        .filter(|cell| cell.len() == 2 && cell[0] >= 0)
+                        .ok_or_else(|| invalid(&line))?;
+                    sparse.push((cell[0] as usize, cell[1]));
+                }
+                "" => {}
+                _ => return Err(invalid(&line)),
+            }
+        }
+
+        let mut memory = match limit {
+            Some(limit) => Memory::with_limit(dense, limit),
+            None => Memory::new(dense),
+        };
+        for (address, value) in sparse {
+            memory.set(address, value);
+        }
+        let mut machine = Intcode::with_memory(memory);
+        machine.instruction_ptr = instruction_ptr;
+        machine.relative_base = relative_base;
+        machine.arithmetic = arithmetic;
+        Ok(Snapshot {
+            machine,
+            input,
+            output,
+        })
+    }
+}
+
+fn join(values: &[i64]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn split(values: &str) -> Option<Vec<i64>> {
+    if values.is_empty() {
+        return Some(Vec::new());
+    }
+    values.split(',').map(|v| v.trim().parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::ExitStatus;
+
+    #[test]
+    fn test_round_trip() {
+        let program = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8].to_vec();
+        let mut comp = Intcode::with_memory_limit(program, 1 << 20);
+        comp.memory.set(100_000, 7);
+        let mut input = VecDeque::new();
+        assert_eq!(
+            comp.step(&mut input).unwrap(),
+            Some(ExitStatus::AwaitingInput)
+        );
+        comp.relative_base = -3;
+        comp.set_arithmetic(Arithmetic::Wrapping);
+
+        let snapshot = Snapshot::new(&comp, &[8], &[1, 2]);
+        let mut saved = Vec::new();
+        snapshot.write_to(&mut saved).unwrap();
+        let restored = Snapshot::read_from(&saved[..]).unwrap();
+
+        assert_eq!(restored.machine.memory, comp.memory);
+        assert_eq!(restored.machine.instruction_ptr, 0);
+        assert_eq!(restored.machine.relative_base, -3);
+        assert_eq!(restored.machine.arithmetic, Arithmetic::Wrapping);
+        assert_eq!(restored.input, [8]);
+        assert_eq!(restored.output, [1, 2]);
+
+        let mut machine = restored.machine;
+        let mut input = VecDeque::from(restored.input);
+        let mut output = restored.output;
+        machine.compute(&mut input, &mut output).unwrap();
+        assert_eq!(output, [1, 2, 1]);
+
+        assert!(Snapshot::read_from(&b"intcode-snapshot 1\nip x\n"[..]).is_err());
+        assert!(Snapshot::read_from(&b"something else\n"[..]).is_err());
+        assert!(Snapshot::read_from(&b"intcode-snapshot 1\narithmetic saturating\n"[..]).is_err());
+        let old = Snapshot::read_from(&b"intcode-snapshot 1\nmemory 99\n"[..]).unwrap();
+        assert_eq!(old.machine.arithmetic, Arithmetic::Checked);
+    }
+}
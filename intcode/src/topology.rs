@@ -0,0 +1,251 @@
+//! A plain-text description of a `Network`.
+//!
+//! Each line is a directive, and `#` starts a comment. Machines may be linked before they are
+//! declared, and program paths are relative to the directory holding the topology file.
+//!
+//! ```text
+//! # Day 7's feedback loop
+//! machine a input.txt 9 0     # name, program, then initial input
+//! machine b input.txt 8
+//! link a b                    # a's outputs go to b's input
+//! link b a
+//! packets c 2                 # c sends packets of an address and two values
+//! idle -1                     # input for machines that have none
+//! ```
+//!
+//! Machines without a `link` or `packets` line keep their outputs.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{load_program, Intcode, Network, Route};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TopologyErrorKind {
+    UnknownDirective(String),
+    /// The directive does not take this many arguments.
+    ArgumentCount(String, usize),
+    BadArgument(String),
+    UnknownMachine(String),
+    DuplicateMachine(String),
+    /// A machine given more than one `link` or `packets` line.
+    DuplicateRoute(String),
+}
+
+impl fmt::Display for TopologyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownDirective(d) => write!(f, "unknown directive {}", d),
+            Self::ArgumentCount(d, n) => write!(f, "{} does not take {} arguments", d, n),
+            Self::BadArgument(a) => write!(f, "bad argument {:?}", a),
+            Self::UnknownMachine(m) => write!(f, "unknown machine {}", m),
+            Self::DuplicateMachine(m) => write!(f, "duplicate machine {}", m),
+            Self::DuplicateRoute(m) => write!(f, "machine {} is routed twice", m),
+        }
+    }
+}
+
+/// A topology error and the (one-based) line it occurred on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyError {
+    pub kind: TopologyErrorKind,
+    pub line: usize,
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl Error for TopologyError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineSpec {
+    pub name: String,
+    pub program: String,
+    pub input: Vec<i64>,
+    pub route: Route,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Topology {
+    pub machines: Vec<MachineSpec>,
+    pub idle_input: Option<i64>,
+}
+
+impl Topology {
+    pub fn parse(source: &str) -> Result<Topology, TopologyError> {
+        let mut topology = Topology::default();
+        let mut indices = HashMap::new();
+        let mut links = Vec::new();
+        let mut routed = HashMap::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let error = |kind| TopologyError {
+                kind,
+                line: number + 1,
+            };
+            let integer = |word: &str| {
+                word.parse::<i64>()
+                    .map_err(|_| error(TopologyErrorKind::BadArgument(word.to_string())))
+            };
+            let words = line
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            let (directive, args) = match words.split_first() {
+                Some((directive, args)) => (*directive, args),
+                None => continue,
+            };
+
+            match (directive, args) {
+                ("machine", [name, program, input @ ..]) => {
+                    if indices.contains_key(*name) {
+                        return Err(error(TopologyErrorKind::DuplicateMachine(name.to_string())));
+                    }
+                    indices.insert(name.to_string(), topology.machines.len());
+                    topology.machines.push(MachineSpec {
+                        name: name.to_string(),
+                        program: program.to_string(),
+                        input: input
+                            .iter()
+                            .map(|word| integer(word))
+                            .collect::<Result<_, _>>()?,
+                        route: Route::Collect,
+                    });
+                }
+                ("link", [from, to]) | ("packets", [from, to]) => {
+                    if routed.insert(from.to_string(), number + 1).is_some() {
+                        return Err(error(TopologyErrorKind::DuplicateRoute(from.to_string())));
+                    }
+                    links.push((number + 1, directive, *from, *to));
+                }
+                ("idle", [value]) => topology.idle_input = Some(integer(value)?),
+                ("machine", _) | ("link", _) | ("packets", _) | ("idle", _) => {
+                    return Err(error(TopologyErrorKind::ArgumentCount(
+                        directive.to_string(),
+                        args.len(),
+                    )))
+                }
+                _ => {
+                    return Err(error(TopologyErrorKind::UnknownDirective(
+                        directive.to_string(),
+                    )))
+                }
+            }
+        }
+
+        for (line, directive, from, to) in links {
+            let error = |kind| TopologyError { kind, line };
+            let index = |name: &str| {
+                indices
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| error(TopologyErrorKind::UnknownMachine(name.to_string())))
+            };
+            let route = match directive {
+                "link" => Route::Link(index(to)?),
+                _ => Route::Packets {
+                    payload: to
+                        .parse()
+                        .map_err(|_| error(TopologyErrorKind::BadArgument(to.to_string())))?,
+                },
+            };
+            topology.machines[index(from)?].route = route;
+        }
+        Ok(topology)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Topology> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The index of the named machine in `machines` and in the built network.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.machines.iter().position(|m| m.name == name)
+    }
+
+    /// Builds the network, reading each program relative to `dir`.
+    pub fn network<P: AsRef<Path>>(&self, dir: P) -> io::Result<Network> {
+        let mut programs = HashMap::new();
+        let mut network = Network::new();
+        network.set_idle_input(self.idle_input);
+        for (i, spec) in self.machines.iter().enumerate() {
+            if !programs.contains_key(&spec.program) {
+                let program = load_program(dir.as_ref().join(&spec.program))?;
+                programs.insert(spec.program.clone(), program);
+            }
+            network.add(Intcode::new(programs[&spec.program].clone()));
+            network.route(i, spec.route);
+            for value in &spec.input {
+                network.send(i, *value);
+            }
+        }
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let topology = Topology::parse(
+            "# a loop\n\
+             machine a amp.txt 9 0\n\
+             link b a\n\
+             machine b amp.txt 8   # comment\n\
+             link a b\n\
+             \n\
+             machine c nic.txt\n\
+             packets c 2\n\
+             idle -1\n",
+        )
+        .unwrap();
+        assert_eq!(topology.machines.len(), 3);
+        assert_eq!(topology.machines[0].input, [9, 0]);
+        assert_eq!(topology.machines[0].route, Route::Link(1));
+        assert_eq!(topology.machines[1].route, Route::Link(0));
+        assert_eq!(topology.machines[2].route, Route::Packets { payload: 2 });
+        assert_eq!(topology.idle_input, Some(-1));
+        assert_eq!(topology.index("c"), Some(2));
+
+        let error = |source| Topology::parse(source).unwrap_err();
+        assert_eq!(
+            error("machine a p.txt\nlink a z\n"),
+            TopologyError {
+                kind: TopologyErrorKind::UnknownMachine(String::from("z")),
+                line: 2
+            }
+        );
+        assert_eq!(
+            error("machine a p.txt x").kind,
+            TopologyErrorKind::BadArgument(String::from("x"))
+        );
+        assert_eq!(
+            error("machine a p.txt\nmachine a q.txt").kind,
+            TopologyErrorKind::DuplicateMachine(String::from("a"))
+        );
+        assert_eq!(
+            error("machine a p.txt\nlink a a\npackets a 2").kind,
+            TopologyErrorKind::DuplicateRoute(String::from("a"))
+        );
+        assert_eq!(
+            error("link a").kind,
+            TopologyErrorKind::ArgumentCount(String::from("link"), 1)
+        );
+        assert_eq!(
+            error("wire a b").kind,
+            TopologyErrorKind::UnknownDirective(String::from("wire"))
+        );
+    }
+}
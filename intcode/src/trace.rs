@@ -0,0 +1,275 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::disasm::Operand;
+use crate::{Intcode, Operation, ParameterMode};
+
+const MAGIC: &[u8; 4] = b"ICTR";
+const VERSION: u8 = 1;
+
+/// One executed instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub instruction_ptr: usize,
+    pub instruction: i64,
+    pub operation: Operation,
+    /// Each parameter resolved through its mode: the value read, or for the parameter the
+    /// operation writes to, the address written. Parameters the instruction didn't read, such as
+    /// the target of a jump not taken, show the value in plain memory.
+    pub operands: Vec<i64>,
+    /// The address and new value of the memory cell written, if any.
+    pub write: Option<(usize, i64)>,
+    /// The new relative base, if the instruction adjusted it.
+    pub relative_base: Option<i64>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}: {}",
+            self.instruction_ptr,
+            self.operation.mnemonic()
+        )?;
+        let modes = self.operation.parameter_modes();
+        for (i, (mode, operand)) in modes.iter().zip(&self.operands).enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            match (mode, Some(i) == self.operation.write_parameter()) {
+                (ParameterMode::Immediate, _) => {
+                    write!(f, "{}{}", separator, Operand(*mode, *operand))?
+                }
+                (_, true) => write!(f, "{}->[{}]", separator, operand)?,
+                (_, false) => write!(f, "{}={}", separator, operand)?,
+            }
+        }
+        if let Some((address, value)) = self.write {
+            write!(f, " | [{}] <- {}", address, value)?;
+        }
+        if let Some(relative_base) = self.relative_base {
+            write!(f, " | rb <- {}", relative_base)?;
+        }
+        Ok(())
+    }
+}
+
+/// A record of every instruction a machine executed while tracing was enabled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// The index of the first entry where the two traces differ, if they differ at all.
+    pub fn first_divergence(&self, other: &Trace) -> Option<usize> {
+        let common = self.entries.len().min(other.entries.len());
+        (0..common)
+            .find(|&i| self.entries[i] != other.entries[i])
+            .or(match self.entries.len() == other.entries.len() {
+                true => None,
+                false => Some(common),
+            })
+    }
+
+    /// One entry per line, such as `0004: ADD =3, #7, ->[10] | [10] <- 10`.
+    pub fn write_text<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for entry in &self.entries {
+            writeln!(writer, "{}", entry)?;
+        }
+        Ok(())
+    }
+
+    /// A compact encoding of zigzag varints, readable with `read_binary`.
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(MAGIC);
+        buffer.push(VERSION);
+        put_varint(&mut buffer, self.entries.len() as i64);
+        for entry in &self.entries {
+            put_varint(&mut buffer, entry.instruction_ptr as i64);
+            put_varint(&mut buffer, entry.instruction);
+            for operand in &entry.operands {
+                put_varint(&mut buffer, *operand);
+            }
+            let flags = entry.write.is_some() as i64 | (entry.relative_base.is_some() as i64) << 1;
+            put_varint(&mut buffer, flags);
+            if let Some((address, value)) = entry.write {
+                put_varint(&mut buffer, address as i64);
+                put_varint(&mut buffer, value);
+            }
+            if let Some(relative_base) = entry.relative_base {
+                put_varint(&mut buffer, relative_base);
+            }
+        }
+        writer.write_all(&buffer)
+    }
+
+    pub fn read_binary<R: Read>(mut reader: R) -> io::Result<Trace> {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        if buffer.len() < 5 || &buffer[..4] != MAGIC || buffer[4] != VERSION {
+            return Err(invalid("not an Intcode trace"));
+        }
+
+        let mut bytes = buffer[5..].iter().copied();
+        let mut next = || get_varint(&mut bytes).ok_or_else(|| invalid("truncated trace"));
+        let count = next()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let instruction_ptr = next()? as usize;
+            let instruction = next()?;
+            let operation =
+                Operation::parse(instruction).map_err(|_| invalid("bad instruction in trace"))?;
+            let operands = operation
+                .parameter_modes()
+                .iter()
+                .map(|_| next())
+                .collect::<io::Result<Vec<_>>>()?;
+            let flags = next()?;
+            let write = match flags & 1 {
+                0 => None,
+                _ => Some((next()? as usize, next()?)),
+            };
+            let relative_base = match flags & 2 {
+                0 => None,
+                _ => Some(next()?),
+            };
+            entries.push(TraceEntry {
+                instruction_ptr,
+                instruction,
+                operation,
+                operands,
+                write,
+                relative_base,
+            });
+        }
+        Ok(Trace { entries })
+    }
+}
+
+fn put_varint(buffer: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    loop {
+        let byte = (zigzag & 0x7f) as u8;
+        zigzag >>= 7;
+        if zigzag == 0 {
+            buffer.push(byte);
+            return;
+        }
+        buffer.push(byte | 0x80);
+    }
+}
+
+fn get_varint<I: Iterator<Item = u8>>(bytes: &mut I) -> Option<i64> {
+    let mut zigzag = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = bytes.next()?;
+        zigzag |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64));
+        }
+    }
+    None
+}
+
+impl Intcode {
+    /// Starts recording every executed instruction, discarding any earlier trace.
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(Trace::default());
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// Stops tracing and returns what was recorded.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Resolves the operands of the instruction about to run. Returns `None` if they can't be
+    /// resolved, in which case executing it will fail anyway.
+    pub(crate) fn trace_entry(&self, instruction: i64, operation: Operation) -> Option<TraceEntry> {
+        let operands = self
+            .operand_addresses(operation)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, (parameter, address))| match address {
+                None => Some(parameter),
+                Some(address) if Some(i) == operation.write_parameter() => Some(address as i64),
+                Some(address) => self.read(address as i64).ok(),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(TraceEntry {
+            instruction_ptr: self.instruction_ptr,
+            instruction,
+            operation,
+            operands,
+            write: None,
+            relative_base: None,
+        })
+    }
+
+    /// Completes `entry` with the effects of the instruction that just ran and records it.
+    pub(crate) fn record_trace(&mut self, mut entry: TraceEntry) {
+        for (i, value) in self.operands_read.drain(..) {
+            entry.operands[i] = value;
+        }
+        if entry.operation.write_parameter().is_some() {
+            entry.write = self.last_write;
+        }
+        if let Operation::ChangeRelativeBase(_) = entry.operation {
+            entry.relative_base = Some(self.relative_base);
+        }
+        if let Some(trace) = self.trace.as_mut() {
+            trace.entries.push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::sink;
+
+    use super::*;
+
+    #[test]
+    fn test_trace() {
+        let mut comp = Intcode::new([109, 3, 203, 8, 1001, 11, 2, 11, 4, 11, 99, 0].to_vec());
+        comp.enable_trace();
+        comp.compute(&mut VecDeque::from([5]), sink()).unwrap();
+        let trace = comp.take_trace().unwrap();
+
+        let mut text = Vec::new();
+        trace.write_text(&mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "0000: ARB #3 | rb <- 3\n\
+             0002: IN ->[11] | [11] <- 5\n\
+             0004: ADD =5, #2, ->[11] | [11] <- 7\n\
+             0008: OUT =7\n\
+             0010: HLT\n"
+        );
+
+        let mut binary = Vec::new();
+        trace.write_binary(&mut binary).unwrap();
+        assert_eq!(Trace::read_binary(&binary[..]).unwrap(), trace);
+        assert!(Trace::read_binary(&binary[..binary.len() - 1]).is_err());
+
+        let mut comp = Intcode::new([109, 3, 203, 8, 1001, 11, 2, 11, 4, 11, 99, 0].to_vec());
+        comp.enable_trace();
+        comp.compute(&mut VecDeque::from([6]), sink()).unwrap();
+        assert_eq!(comp.trace().unwrap().first_divergence(&trace), Some(1));
+        assert_eq!(trace.first_divergence(&trace), None);
+    }
+
+    #[test]
+    fn test_varint() {
+        for &value in [0, 1, -1, 63, -64, 1 << 40, i64::MAX, i64::MIN].iter() {
+            let mut buffer = Vec::new();
+            put_varint(&mut buffer, value);
+            assert_eq!(get_varint(&mut buffer.into_iter()), Some(value));
+        }
+    }
+}
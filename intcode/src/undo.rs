@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+
+use crate::{ErrorKind, Intcode, IntcodeInput, Operation};
+
+/// What one executed instruction changed, enough to put the machine back as it was.
+#[derive(Debug, Clone)]
+pub(crate) struct UndoRecord {
+    instruction_ptr: usize,
+    relative_base: i64,
+    operation: Operation,
+    /// The address written and the value it held before.
+    write: Option<(usize, i64)>,
+    /// The value read, if the instruction consumed input.
+    input: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct UndoLog {
+    records: VecDeque<UndoRecord>,
+    capacity: Option<usize>,
+}
+
+/// Input taken from the first source until it runs dry, then from the second.
+pub(crate) struct Chain<A, B>(pub A, pub B);
+
+impl<A: IntcodeInput, B: IntcodeInput> IntcodeInput for Chain<A, B> {
+    fn read(&mut self) -> Result<Option<i64>, ErrorKind> {
+        match self.0.read()? {
+            Some(value) => Ok(Some(value)),
+            None => self.1.read(),
+        }
+    }
+}
+
+impl Intcode {
+    /// Starts logging each instruction's effects so they can be undone with `step_back`,
+    /// keeping at most `capacity` instructions if given. Any earlier log is discarded.
+    pub fn enable_undo(&mut self, capacity: Option<usize>) {
+        self.undo = Some(UndoLog {
+            records: VecDeque::new(),
+            capacity,
+        });
+    }
+
+    pub fn disable_undo(&mut self) {
+        self.undo = None;
+    }
+
+    /// How many instructions can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.undo.as_ref().map_or(0, |log| log.records.len())
+    }
+
+    /// Input given back by `step_back`, which is read again before any new input.
+    pub fn unread_input(&self) -> &VecDeque<i64> {
+        &self.unread
+    }
+
+    /// Undoes the last logged instruction, returning it, or `None` if there is nothing to undo.
+    /// Values it consumed become unread input again. Outputs can't be recalled, so stepping
+    /// back over an `Output` is up to the caller to account for.
+    pub fn step_back(&mut self) -> Option<Operation> {
+        let record = self.undo.as_mut()?.records.pop_back()?;
+        if let Some((address, old)) = record.write {
+            self.memory.set(address, old);
+        }
+        if let Some(value) = record.input {
+            self.unread.push_front(value);
+        }
+        self.instruction_ptr = record.instruction_ptr;
+        self.relative_base = record.relative_base;
+        if self.loop_detector.is_some() {
+            self.enable_loop_detection();
+        }
+        Some(record.operation)
+    }
+
+    /// Steps back until just before the last logged write to `address`, returning the address
+    /// of the instruction that made it. If no logged instruction wrote there, the machine is
+    /// left at the start of the log and `None` is returned.
+    ///
+    /// To find where a bad output came from, step back over the `Output` and run back to the
+    /// last write of the cell it read.
+    pub fn run_back_to_write(&mut self, address: usize) -> Option<usize> {
+        while self.step_back().is_some() {
+            if self.write_address() == Some(address) {
+                return Some(self.instruction_ptr);
+            }
+        }
+        None
+    }
+
+    /// Forgets every logged instruction, for when the machine has changed in a way that can't
+    /// be undone.
+    pub(crate) fn clear_undo(&mut self) {
+        if let Some(log) = self.undo.as_mut() {
+            log.records.clear();
+        }
+    }
+
+    /// The state needed to undo `operation`, which is about to run.
+    pub(crate) fn undo_record(&self, operation: Operation) -> Option<UndoRecord> {
+        let write = match operation.write_parameter() {
+            Some(i) => {
+                let address = self.operand_addresses(operation)?[i].1?;
+                Some((address, self.memory.get(address)))
+            }
+            None => None,
+        };
+        Some(UndoRecord {
+            instruction_ptr: self.instruction_ptr,
+            relative_base: self.relative_base,
+            operation,
+            write,
+            input: None,
+        })
+    }
+
+    /// Logs `record` now that its instruction has run.
+    pub(crate) fn record_undo(&mut self, mut record: UndoRecord) {
+        if let Operation::Input(_) = record.operation {
+            record.input = self.last_write.map(|(_, value)| value);
+        }
+        if let Some(log) = self.undo.as_mut() {
+            if log.capacity == Some(log.records.len()) {
+                log.records.pop_front();
+            }
+            log.records.push_back(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{empty, sink};
+
+    use super::*;
+    use crate::{ExitStatus, ParameterMode};
+
+    #[test]
+    fn test_step_back() {
+        // Doubles its input, adds 3 and outputs the result.
+        let program = [
+            109, 5, 3, 15, 1002, 15, 2, 16, 1001, 16, 3, 16, 4, 16, 99, 0, 0,
+        ]
+        .to_vec();
+        let mut comp = Intcode::new(program.clone());
+        comp.enable_undo(None);
+        let mut input = VecDeque::from([4]);
+        assert_eq!(
+            comp.run_until_output(&mut input).unwrap(),
+            ExitStatus::Output(11)
+        );
+        assert_eq!(comp.undo_len(), 5);
+
+        assert_eq!(
+            comp.step_back(),
+            Some(Operation::Output(ParameterMode::Position))
+        );
+        assert_eq!(comp.run_back_to_write(16), Some(8));
+        assert_eq!(comp.memory[16], 8);
+        assert_eq!(comp.run_back_to_write(16), Some(4));
+        assert_eq!(comp.memory[16], 0);
+
+        while comp.step_back().is_some() {}
+        assert_eq!(comp.instruction_ptr(), 0);
+        assert_eq!(comp.relative_base(), 0);
+        assert!(comp
+            .memory
+            .cells()
+            .map(|(_, value)| value)
+            .eq(program.iter().copied()));
+        assert_eq!(comp.unread_input(), &[4]);
+        assert_eq!(comp.run_back_to_write(16), None);
+
+        // The given-back input is read again, ahead of new input.
+        let mut output = Vec::new();
+        comp.compute(&mut VecDeque::from([100]), &mut output)
+            .unwrap();
+        assert_eq!(output, [11]);
+        assert!(comp.unread_input().is_empty());
+
+        let mut comp = Intcode::new(program);
+        comp.enable_undo(Some(2));
+        comp.compute(&mut VecDeque::from([1]), sink()).unwrap();
+        assert_eq!(comp.undo_len(), 2);
+        comp.disable_undo();
+        assert_eq!(comp.step_back(), None);
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+    }
+}
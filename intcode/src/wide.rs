@@ -0,0 +1,266 @@
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+
+use crate::processor::{self, Processor};
+use crate::{
+    Arithmetic, ErrorKind, ExitStatus, IntcodeError, IntcodeInput, IntcodeOutput, Memory,
+    ParameterMode, Word,
+};
+
+/// Instruction words are decoded from their last five digits, which hold the opcode and every
+/// parameter mode.
+const INSTRUCTION_DIGITS: i64 = 100_000;
+
+/// Big integers never overflow, so the arithmetic policy doesn't apply to them.
+impl Word for BigInt {
+    fn add(self, other: Self, _: Arithmetic) -> Result<Self, ErrorKind> {
+        Ok(self + other)
+    }
+
+    fn mul(self, other: Self, _: Arithmetic) -> Result<Self, ErrorKind> {
+        Ok(self * other)
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        self.to_i64()
+    }
+
+    fn saturating_i64(&self) -> i64 {
+        self.to_i64().unwrap_or(match self.sign() {
+            Sign::Minus => i64::MIN,
+            _ => i64::MAX,
+        })
+    }
+
+    fn instruction(&self) -> i64 {
+        (self % INSTRUCTION_DIGITS)
+            .to_i64()
+            .expect("Remainder fits in an i64.")
+    }
+}
+
+/// An Intcode machine whose memory holds arbitrary-precision integers, so arithmetic never
+/// overflows. It runs the same instructions as `Intcode`, but without custom opcodes, devices
+/// or the tracing and debugging tools.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WideIntcode {
+    pub memory: Memory<BigInt>,
+    instruction_ptr: usize,
+    relative_base: BigInt,
+}
+
+impl WideIntcode {
+    pub fn new(program: Vec<BigInt>) -> Self {
+        Self::with_memory(Memory::new(program))
+    }
+
+    /// A machine running a program given as `i64`s.
+    pub fn from_program(program: &[i64]) -> Self {
+        Self::new(program.iter().map(|&value| BigInt::from(value)).collect())
+    }
+
+    /// A machine whose memory is addressable only below `limit`.
+    pub fn with_memory_limit(program: Vec<BigInt>, limit: usize) -> Self {
+        Self::with_memory(Memory::with_limit(program, limit))
+    }
+
+    pub fn with_memory(memory: Memory<BigInt>) -> Self {
+        WideIntcode {
+            memory,
+            ..Self::default()
+        }
+    }
+
+    pub fn instruction_ptr(&self) -> usize {
+        self.instruction_ptr
+    }
+
+    pub fn relative_base(&self) -> &BigInt {
+        &self.relative_base
+    }
+
+    /// Runs until the program needs input it doesn't have or terminates, passing outputs to
+    /// `output`.
+    pub fn compute<I, O>(
+        &mut self,
+        mut input: I,
+        mut output: O,
+    ) -> Result<ExitStatus<BigInt>, IntcodeError>
+    where
+        I: IntcodeInput<BigInt>,
+        O: IntcodeOutput<BigInt>,
+    {
+        loop {
+            let instruction_ptr = self.instruction_ptr;
+            match self.step(&mut input)? {
+                Some(ExitStatus::Output(value)) => {
+                    output
+                        .write(value)
+                        .map_err(|e| self.error_at(instruction_ptr, e))?;
+                }
+                Some(status) => return Ok(status),
+                None => {}
+            }
+        }
+    }
+
+    /// Runs until the program outputs a value, needs input it doesn't have, or terminates.
+    pub fn run_until_output<I>(&mut self, mut input: I) -> Result<ExitStatus<BigInt>, IntcodeError>
+    where
+        I: IntcodeInput<BigInt>,
+    {
+        loop {
+            if let Some(status) = self.step(&mut input)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Executes exactly one instruction. Returns `None` if the machine is still running, or the
+    /// status if it output a value, needs input, or terminated.
+    pub fn step<I>(&mut self, mut input: I) -> Result<Option<ExitStatus<BigInt>>, IntcodeError>
+    where
+        I: IntcodeInput<BigInt>,
+    {
+        let instruction_ptr = self
+            .address(&BigInt::from(self.instruction_ptr))
+            .map_err(|e| self.error_at(self.instruction_ptr, e))?;
+        let operation = self
+            .memory
+            .decode(instruction_ptr)
+            .map_err(|e| self.error_at(instruction_ptr, e))?;
+        processor::execute(self, operation, &mut input)
+            .map_err(|e| self.error_at(instruction_ptr, e))
+    }
+
+    /// Errors report instruction words too large for an `i64` as saturated.
+    fn error_at(&self, instruction_ptr: usize, kind: ErrorKind) -> IntcodeError {
+        IntcodeError {
+            kind,
+            instruction_ptr,
+            instruction: self.memory.get(instruction_ptr).saturating_i64(),
+        }
+    }
+
+    fn address(&self, address: &BigInt) -> Result<usize, ErrorKind> {
+        match address.as_i64() {
+            Some(a) if a >= 0 && self.memory.contains(a as usize) => Ok(a as usize),
+            _ => Err(ErrorKind::InvalidAddress(address.saturating_i64())),
+        }
+    }
+
+    /// The address the parameter at `offset` refers to, which for an immediate parameter is its
+    /// own cell.
+    fn parameter_address(&self, offset: usize, mode: ParameterMode) -> Result<usize, ErrorKind> {
+        let cell = self.address(&BigInt::from(self.instruction_ptr + offset))?;
+        let parameter = self.memory.get(cell);
+        match mode {
+            ParameterMode::Position => self.address(&parameter),
+            ParameterMode::Immediate => Ok(cell),
+            ParameterMode::Relative => self.address(&(parameter + &self.relative_base)),
+        }
+    }
+}
+
+impl Processor for WideIntcode {
+    type Word = BigInt;
+
+    fn arithmetic(&self) -> Arithmetic {
+        Arithmetic::Checked
+    }
+
+    fn parameter(&mut self, offset: usize, mode: ParameterMode) -> Result<BigInt, ErrorKind> {
+        Ok(self.memory.get(self.parameter_address(offset, mode)?))
+    }
+
+    fn write_parameter(
+        &mut self,
+        offset: usize,
+        value: BigInt,
+        mode: ParameterMode,
+    ) -> Result<(), ErrorKind> {
+        if mode == ParameterMode::Immediate {
+            return Err(ErrorKind::ImmediateWrite);
+        }
+        let address = self.parameter_address(offset, mode)?;
+        self.memory.set(address, value);
+        Ok(())
+    }
+
+    fn jump(&mut self, target: BigInt) -> Result<(), ErrorKind> {
+        self.instruction_ptr = self.address(&target)?;
+        Ok(())
+    }
+
+    fn advance(&mut self, by: usize) {
+        self.instruction_ptr += by;
+    }
+
+    fn relative_base(&self) -> BigInt {
+        self.relative_base.clone()
+    }
+
+    fn set_relative_base(&mut self, relative_base: BigInt) {
+        self.relative_base = relative_base;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::{empty, sink};
+
+    use super::*;
+
+    #[test]
+    fn test_wide() {
+        // Squares its input twice and outputs the result.
+        let program = [3, 13, 2, 13, 13, 13, 2, 13, 13, 13, 4, 13, 99, 0];
+        let mut comp = WideIntcode::from_program(&program);
+        let mut input = VecDeque::new();
+        let mut output = Vec::new();
+        assert_eq!(
+            comp.compute(&mut input, &mut output).unwrap(),
+            ExitStatus::AwaitingInput
+        );
+        input.push_back(BigInt::from(1i64 << 40));
+        assert_eq!(
+            comp.run_until_output(&mut input).unwrap(),
+            ExitStatus::Output(BigInt::from(1) << 160)
+        );
+        assert_eq!(comp.step(&mut input).unwrap(), Some(ExitStatus::Terminated));
+
+        // Relative addressing, comparisons and jumps, from day 9's quine.
+        let quine = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut comp = WideIntcode::from_program(&quine);
+        let mut output = Vec::new();
+        comp.compute(empty(), &mut output).unwrap();
+        assert_eq!(output, WideIntcode::from_program(&quine).memory.to_vec());
+
+        let mut comp = WideIntcode::from_program(&[1101, 1, 1, 7, 4, -1, 99, 0]);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidAddress(-1));
+        assert_eq!(error.instruction_ptr, 4);
+        let mut comp = WideIntcode::new([BigInt::from(4), BigInt::from(1) << 70].to_vec());
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidAddress(i64::MAX));
+        let mut comp =
+            WideIntcode::with_memory_limit([BigInt::from(104), BigInt::from(1)].to_vec(), 2);
+        let error = comp.compute(empty(), sink()).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidAddress(2));
+
+        // Words too large for an `i64` still decode from their last digits.
+        let mut comp = WideIntcode::new([BigInt::from(99) + BigInt::from(10).pow(30)].to_vec());
+        assert_eq!(
+            comp.compute(empty(), sink()).unwrap(),
+            ExitStatus::Terminated
+        );
+        let error = WideIntcode::new([BigInt::from(10).pow(30)].to_vec())
+            .compute(empty(), sink())
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownOpcode(0));
+        assert_eq!(error.instruction, i64::MAX);
+    }
+}